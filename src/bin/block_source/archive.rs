@@ -0,0 +1,74 @@
+use crate::block_source::BLOCK_SOURCE_TARGET;
+use crate::xblock::manifest::is_archive_complete;
+use crate::xblock::reader::{archive_height, read_archive, ARCHIVE_SIZE};
+use fastnear_primitives::block_with_tx_hash::BlockWithTxHashes;
+use fastnear_primitives::near_primitives::types::BlockHeight;
+use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+pub struct ArchiveConfig {
+    /// The `WRITE_DATA_PATH` folder of `lake-convert`.
+    pub path: String,
+    /// Exclusive.
+    pub to_block: BlockHeight,
+}
+
+impl ArchiveConfig {
+    pub fn from_env() -> Self {
+        Self {
+            path: env::var("ARCHIVE_DATA_PATH").expect("ARCHIVE_DATA_PATH is required"),
+            to_block: env::var("TO_BLOCK")
+                .expect("TO_BLOCK (exclusive) is required")
+                .parse()
+                .expect("Failed to parse TO_BLOCK"),
+        }
+    }
+}
+
+pub(crate) async fn start(
+    config: ArchiveConfig,
+    start_block_height: BlockHeight,
+    blocks_sink: mpsc::Sender<BlockWithTxHashes>,
+    is_running: Arc<AtomicBool>,
+) {
+    let path = Arc::new(config.path);
+    // Every archive has a manifest once converted, even without blocks, so a missing or incomplete
+    // archive is a gap in the conversion and must not be indexed as an empty range.
+    let read = |archive_height: BlockHeight| {
+        let path = path.clone();
+        tokio::task::spawn_blocking(move || {
+            assert!(
+                is_archive_complete(&path, archive_height),
+                "Archive {} is missing or incomplete",
+                archive_height
+            );
+            read_archive(&path, archive_height)
+                .expect("Failed to read the archive")
+                .expect("The archive is missing")
+        })
+    };
+    // Reading the next archive while the blocks of the current one are being processed.
+    let mut archive_height = archive_height(start_block_height);
+    let mut next_archive = (archive_height < config.to_block).then(|| read(archive_height));
+    while let Some(archive) = next_archive {
+        let blocks = archive.await.unwrap();
+        let next_archive_height = archive_height + ARCHIVE_SIZE;
+        next_archive = (next_archive_height < config.to_block).then(|| read(next_archive_height));
+        for block in blocks {
+            let block_height = block.block.header.height;
+            if block_height < start_block_height {
+                continue;
+            }
+            if block_height >= config.to_block || !is_running.load(Ordering::SeqCst) {
+                return;
+            }
+            tracing::log::debug!(target: BLOCK_SOURCE_TARGET, "Processing block: {}", block_height);
+            if blocks_sink.send(block.into()).await.is_err() {
+                return;
+            }
+        }
+        archive_height = next_archive_height;
+    }
+}
@@ -0,0 +1,81 @@
+use crate::block_source::BLOCK_SOURCE_TARGET;
+use crate::lake_reader::{LakeGroup, LakeReader, GROUP_SIZE};
+use fastnear_primitives::block_with_tx_hash::BlockWithTxHashes;
+use fastnear_primitives::near_indexer_primitives::StreamerMessage;
+use fastnear_primitives::near_primitives::types::BlockHeight;
+use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+pub struct LakeConfig {
+    /// A local folder or `s3://<bucket>/<prefix>`.
+    pub path: String,
+    /// Exclusive.
+    pub to_block: BlockHeight,
+}
+
+impl LakeConfig {
+    pub fn from_env() -> Self {
+        Self {
+            path: env::var("LAKE_DATA_PATH").expect("LAKE_DATA_PATH is required"),
+            to_block: env::var("TO_BLOCK")
+                .expect("TO_BLOCK (exclusive) is required")
+                .parse()
+                .expect("Failed to parse TO_BLOCK"),
+        }
+    }
+}
+
+pub(crate) async fn start(
+    config: LakeConfig,
+    start_block_height: BlockHeight,
+    blocks_sink: mpsc::Sender<BlockWithTxHashes>,
+    is_running: Arc<AtomicBool>,
+) {
+    let mut reader = LakeReader::new(&config.path, start_block_height, config.to_block)
+        .await
+        .expect("Failed to open the lake");
+    while let Some((group_height, group)) =
+        reader.next_group().await.expect("Failed to read the lake")
+    {
+        let from_block = group_height.max(start_block_height);
+        let to_block = (group_height + GROUP_SIZE).min(config.to_block);
+        // Parsing the JSONs of a group is CPU heavy, so it's done off the async workers.
+        let blocks =
+            tokio::task::spawn_blocking(move || parse_blocks(&group, from_block, to_block))
+                .await
+                .unwrap();
+        for block in blocks {
+            if !is_running.load(Ordering::SeqCst) {
+                return;
+            }
+            if blocks_sink.send(block).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Returns the blocks of the group in the given range, skipping the missing blocks.
+fn parse_blocks(
+    group: &LakeGroup,
+    from_block: BlockHeight,
+    to_block: BlockHeight,
+) -> Vec<BlockWithTxHashes> {
+    let mut blocks = vec![];
+    for block_height in from_block..to_block {
+        tracing::log::debug!(target: BLOCK_SOURCE_TARGET, "Processing block: {}", block_height);
+        let Some((block_str, shard_strs)) = group.block(block_height) else {
+            tracing::log::debug!(target: BLOCK_SOURCE_TARGET, "Block not found: {}", block_height);
+            continue;
+        };
+        let block = serde_json::from_str(block_str).unwrap();
+        let shards = shard_strs
+            .into_iter()
+            .map(|s| serde_json::from_str(s).unwrap())
+            .collect::<Vec<_>>();
+        blocks.push(StreamerMessage { block, shards }.into());
+    }
+    blocks
+}
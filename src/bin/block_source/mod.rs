@@ -0,0 +1,102 @@
+mod archive;
+mod lake;
+mod neardata;
+mod redis_stream;
+
+pub use archive::ArchiveConfig;
+pub use lake::LakeConfig;
+pub use neardata::NeardataConfig;
+pub use redis_stream::RedisConfig;
+
+use fastnear_primitives::block_with_tx_hash::BlockWithTxHashes;
+use fastnear_primitives::near_primitives::types::BlockHeight;
+use std::env;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+const BLOCK_SOURCE_TARGET: &str = "block_source";
+const BLOCKS_CHANNEL_SIZE: usize = 100;
+
+/// A source of blocks. Every source streams `BlockWithTxHashes` in the increasing order of block
+/// heights, starting from the requested block height.
+pub enum BlockSource {
+    /// The `final_blocks` stream in Redis.
+    Redis(RedisConfig),
+    /// A local folder with the NEAR Lake layout (`block/` and shard folders with tgz files).
+    Lake(LakeConfig),
+    /// The neardata.xyz fetcher.
+    Neardata(NeardataConfig),
+    /// A local folder with the borsh `XBlock` archives written by `lake-convert`.
+    Archive(ArchiveConfig),
+}
+
+impl BlockSource {
+    /// Selects the source based on the `BLOCK_SOURCE` env var, or the given default if not set.
+    pub fn from_env(default_source: &str) -> Self {
+        let source = env::var("BLOCK_SOURCE").unwrap_or(default_source.to_string());
+        match source.as_str() {
+            "redis" => BlockSource::Redis(RedisConfig::from_env()),
+            "lake" => BlockSource::Lake(LakeConfig::from_env()),
+            "neardata" => BlockSource::Neardata(NeardataConfig::from_env()),
+            "archive" => BlockSource::Archive(ArchiveConfig::from_env()),
+            _ => panic!(
+                "Unknown BLOCK_SOURCE `{}`. Expected `redis`, `lake`, `neardata` or `archive`",
+                source
+            ),
+        }
+    }
+
+    pub fn streamer(
+        self,
+        start_block_height: BlockHeight,
+        is_running: Arc<AtomicBool>,
+    ) -> mpsc::Receiver<BlockWithTxHashes> {
+        self.spawn_streamer(start_block_height, is_running).0
+    }
+
+    /// Same as `streamer`, but also returns the handle of the streaming task, e.g. to tell whether
+    /// the stream ended because the source was fully read or because the task panicked.
+    pub fn spawn_streamer(
+        self,
+        start_block_height: BlockHeight,
+        is_running: Arc<AtomicBool>,
+    ) -> (mpsc::Receiver<BlockWithTxHashes>, JoinHandle<()>) {
+        tracing::log::info!(target: BLOCK_SOURCE_TARGET, "Streaming blocks from {} starting at #{}", self.name(), start_block_height);
+        let (sender, receiver) = mpsc::channel(BLOCKS_CHANNEL_SIZE);
+        let handle = match self {
+            BlockSource::Redis(config) => tokio::spawn(redis_stream::start(
+                config,
+                start_block_height,
+                sender,
+                is_running,
+            )),
+            BlockSource::Lake(config) => {
+                tokio::spawn(lake::start(config, start_block_height, sender, is_running))
+            }
+            BlockSource::Neardata(config) => tokio::spawn(neardata::start(
+                config,
+                start_block_height,
+                sender,
+                is_running,
+            )),
+            BlockSource::Archive(config) => tokio::spawn(archive::start(
+                config,
+                start_block_height,
+                sender,
+                is_running,
+            )),
+        };
+        (receiver, handle)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BlockSource::Redis(_) => "redis",
+            BlockSource::Lake(_) => "lake",
+            BlockSource::Neardata(_) => "neardata",
+            BlockSource::Archive(_) => "archive",
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use fastnear_neardata_fetcher::fetcher;
+use fastnear_primitives::block_with_tx_hash::BlockWithTxHashes;
+use fastnear_primitives::near_primitives::types::BlockHeight;
+use fastnear_primitives::types::ChainId;
+use std::env;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+pub struct NeardataConfig {
+    pub chain_id: ChainId,
+    pub num_threads: u64,
+}
+
+impl NeardataConfig {
+    pub fn from_env() -> Self {
+        Self {
+            chain_id: ChainId::try_from(env::var("CHAIN_ID").expect("CHAIN_ID is not set"))
+                .expect("Invalid chain id"),
+            num_threads: env::var("NUM_THREADS")
+                .map(|s| s.parse::<u64>().expect("Failed to parse NUM_THREADS"))
+                .unwrap_or(4),
+        }
+    }
+}
+
+pub(crate) async fn start(
+    config: NeardataConfig,
+    start_block_height: BlockHeight,
+    blocks_sink: mpsc::Sender<BlockWithTxHashes>,
+    is_running: Arc<AtomicBool>,
+) {
+    let config = fetcher::FetcherConfig {
+        num_threads: config.num_threads,
+        start_block_height,
+        chain_id: config.chain_id,
+    };
+    fetcher::start_fetcher(None, config, blocks_sink, is_running).await;
+}
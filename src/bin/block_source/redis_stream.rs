@@ -0,0 +1,90 @@
+use crate::block_source::BLOCK_SOURCE_TARGET;
+use crate::redis_db::RedisDB;
+use fastnear_primitives::block_with_tx_hash::BlockWithTxHashes;
+use fastnear_primitives::near_primitives::types::BlockHeight;
+use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+const FINAL_BLOCKS_KEY: &str = "final_blocks";
+const BLOCK_KEY: &str = "block";
+
+pub struct RedisConfig {
+    pub redis_url: String,
+}
+
+impl RedisConfig {
+    pub fn from_env() -> Self {
+        Self {
+            redis_url: env::var("REDIS_URL").expect("Missing REDIS_URL env var"),
+        }
+    }
+
+    /// Returns the height of the oldest block still available in the stream.
+    pub async fn first_block_height(&self) -> redis::RedisResult<Option<BlockHeight>> {
+        let mut redis_db = RedisDB::new(Some(self.redis_url.clone())).await;
+        let res = redis_db.xread(1, FINAL_BLOCKS_KEY, "0").await?;
+        Ok(res
+            .into_iter()
+            .next()
+            .map(|(id, _key_values)| id_to_block_height(&id)))
+    }
+}
+
+fn id_to_block_height(id: &str) -> BlockHeight {
+    id.split_once('-')
+        .expect("Invalid stream ID")
+        .0
+        .parse()
+        .expect("Invalid block height in stream ID")
+}
+
+pub(crate) async fn start(
+    config: RedisConfig,
+    start_block_height: BlockHeight,
+    blocks_sink: mpsc::Sender<BlockWithTxHashes>,
+    is_running: Arc<AtomicBool>,
+) {
+    // The stream is trimmed, so the blocks before its first block are gone. The block height 0
+    // means the start of the stream.
+    let first_block_height = config
+        .first_block_height()
+        .await
+        .expect("Failed to get the first block from Redis");
+    if let Some(first_block_height) = first_block_height {
+        if start_block_height > 0 && first_block_height > start_block_height {
+            panic!(
+                "The redis stream starts at #{}, after the requested block #{}",
+                first_block_height, start_block_height
+            );
+        }
+    }
+    let mut redis_db = RedisDB::new(Some(config.redis_url)).await;
+    // XREAD returns entries strictly after the given ID.
+    let mut last_id = start_block_height
+        .checked_sub(1)
+        .map(|h| format!("{}-0", h))
+        .unwrap_or("0".to_string());
+    while is_running.load(Ordering::SeqCst) {
+        let res = redis_db.xread(1, FINAL_BLOCKS_KEY, &last_id).await;
+        let res = match res {
+            Ok(res) => res,
+            Err(err) => {
+                tracing::log::error!(target: BLOCK_SOURCE_TARGET, "Error: {}", err);
+                tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
+                let _ = redis_db.reconnect().await;
+                continue;
+            }
+        };
+        let (id, key_values) = res.into_iter().next().unwrap();
+        assert_eq!(key_values.len(), 1, "Expected 1 key-value pair");
+        let (key, value) = key_values.into_iter().next().unwrap();
+        assert_eq!(key, BLOCK_KEY, "Expected key to be block");
+        let block: BlockWithTxHashes = serde_json::from_str(&value).unwrap();
+        if blocks_sink.send(block).await.is_err() {
+            break;
+        }
+        last_id = id;
+    }
+}
@@ -0,0 +1,74 @@
+use ring::rand::{SecureRandom, SystemRandom};
+use std::io;
+use std::path::Path;
+
+/// Saves the contract code as `<code_hash>.wasm` in the given folder, unless it's already there.
+/// The code is written to a temporary file with a unique name first, so a partially written file
+/// is never visible and concurrent writers of the same code don't collide.
+pub fn store_contract_code(path: &str, code_hash: &str, code: &[u8]) -> io::Result<()> {
+    let file_path = format!("{}/{}.wasm", path, code_hash);
+    if Path::new(&file_path).exists() {
+        return Ok(());
+    }
+    std::fs::create_dir_all(path)?;
+    let mut suffix = [0u8; 8];
+    SystemRandom::new()
+        .fill(&mut suffix)
+        .map_err(|_| io::Error::new(io::ErrorKind::Other, "Failed to generate a random suffix"))?;
+    let tmp_file_path = format!(
+        "{}.{}.{}.tmp",
+        file_path,
+        std::process::id(),
+        hex::encode(suffix)
+    );
+    std::fs::write(&tmp_file_path, code)?;
+    match std::fs::rename(&tmp_file_path, &file_path) {
+        Ok(()) => Ok(()),
+        // The same code was stored by another writer in the meantime.
+        Err(_) if Path::new(&file_path).exists() => std::fs::remove_file(&tmp_file_path),
+        Err(err) => {
+            let _ = std::fs::remove_file(&tmp_file_path);
+            Err(err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file_names(path: &Path) -> Vec<String> {
+        let mut names = std::fs::read_dir(path)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_store_contract_code() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("codes");
+        let path = path.to_str().unwrap();
+        store_contract_code(path, "hash", b"code").unwrap();
+        assert_eq!(
+            std::fs::read(format!("{}/hash.wasm", path)).unwrap(),
+            b"code"
+        );
+        assert_eq!(file_names(Path::new(path)), vec!["hash.wasm"]);
+    }
+
+    #[test]
+    fn test_store_contract_code_keeps_existing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        std::fs::write(format!("{}/hash.wasm", path), b"existing").unwrap();
+        store_contract_code(path, "hash", b"code").unwrap();
+        assert_eq!(
+            std::fs::read(format!("{}/hash.wasm", path)).unwrap(),
+            b"existing"
+        );
+        assert_eq!(file_names(dir.path()), vec!["hash.wasm"]);
+    }
+}
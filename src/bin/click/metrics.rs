@@ -0,0 +1,193 @@
+use prometheus::{
+    exponential_buckets, register_histogram_vec, register_int_counter_vec, Encoder, Histogram,
+    HistogramVec, IntCounter, IntCounterVec, TextEncoder,
+};
+use std::env;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+const METRICS_TARGET: &str = "metrics";
+
+struct FlushMetrics {
+    flushes: IntCounterVec,
+    rows: IntCounterVec,
+    rows_per_flush: HistogramVec,
+    duration: HistogramVec,
+    latency: HistogramVec,
+}
+
+static FLUSH_METRICS: OnceLock<FlushMetrics> = OnceLock::new();
+
+fn flush_metrics() -> &'static FlushMetrics {
+    FLUSH_METRICS.get_or_init(|| FlushMetrics {
+        flushes: register_int_counter_vec!(
+            "clickhouse_flushes_total",
+            "Number of flushes of the buffered blocks",
+            &["sink"]
+        )
+        .unwrap(),
+        rows: register_int_counter_vec!(
+            "clickhouse_flushed_rows_total",
+            "Number of rows flushed into all tables",
+            &["sink"]
+        )
+        .unwrap(),
+        rows_per_flush: register_histogram_vec!(
+            "clickhouse_flush_rows",
+            "Number of rows flushed into all tables by one flush",
+            &["sink"],
+            exponential_buckets(10.0, 4.0, 10).unwrap()
+        )
+        .unwrap(),
+        duration: register_histogram_vec!(
+            "clickhouse_flush_duration_seconds",
+            "Time to insert the buffered rows and the checkpoint",
+            &["sink"],
+            exponential_buckets(0.01, 2.0, 14).unwrap()
+        )
+        .unwrap(),
+        latency: register_histogram_vec!(
+            "clickhouse_flush_latency_seconds",
+            "Time from buffering the first block of a flush until the flush is done",
+            &["sink"],
+            exponential_buckets(0.1, 2.0, 14).unwrap()
+        )
+        .unwrap(),
+    })
+}
+
+/// Prometheus metrics of the flushes of one indexer. The metrics are shared by all `ClickDB`s with
+/// the same metrics label, e.g. the `lake-indexer` workers, which have a checkpoint sink per range.
+pub struct FlushStats {
+    flushes: IntCounter,
+    rows: IntCounter,
+    rows_per_flush: Histogram,
+    duration: Histogram,
+    latency: Histogram,
+}
+
+impl FlushStats {
+    pub fn new(sink: &str) -> Self {
+        let metrics = flush_metrics();
+        Self {
+            flushes: metrics.flushes.with_label_values(&[sink]),
+            rows: metrics.rows.with_label_values(&[sink]),
+            rows_per_flush: metrics.rows_per_flush.with_label_values(&[sink]),
+            duration: metrics.duration.with_label_values(&[sink]),
+            latency: metrics.latency.with_label_values(&[sink]),
+        }
+    }
+
+    /// Records a flush of the given number of rows. The latency is the time since the first block
+    /// of the flush was buffered.
+    pub fn observe(&self, num_rows: usize, duration: Duration, latency: Duration) {
+        self.flushes.inc();
+        self.rows.inc_by(num_rows as u64);
+        self.rows_per_flush.observe(num_rows as f64);
+        self.duration.observe(duration.as_secs_f64());
+        self.latency.observe(latency.as_secs_f64());
+    }
+
+    pub fn num_flushes(&self) -> u64 {
+        self.flushes.get()
+    }
+
+    pub fn num_rows(&self) -> u64 {
+        self.rows.get()
+    }
+}
+
+/// Serves the metrics in the Prometheus text format on `METRICS_PORT`, if it's set.
+pub fn start_metrics_server() {
+    let Ok(port) = env::var("METRICS_PORT") else {
+        return;
+    };
+    let port: u16 = port.parse().expect("Invalid METRICS_PORT");
+    tokio::spawn(async move {
+        let listener = TcpListener::bind(("0.0.0.0", port))
+            .await
+            .expect("Failed to bind METRICS_PORT");
+        tracing::log::info!(target: METRICS_TARGET, "Serving metrics on port {}", port);
+        serve(listener).await;
+    });
+}
+
+/// Responds to every connection with the metrics, regardless of the request path.
+async fn serve(listener: TcpListener) {
+    loop {
+        let mut stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(err) => {
+                tracing::log::warn!(target: METRICS_TARGET, "Failed to accept a connection: {}", err);
+                continue;
+            }
+        };
+        tokio::spawn(async move {
+            // The request is not parsed, but it has to be read before responding.
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request).await;
+            let body = encode_metrics();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                TextEncoder::new().format_type(),
+                body.len()
+            );
+            let _ = stream.write_all(response.as_bytes()).await;
+            let _ = stream.write_all(&body).await;
+        });
+    }
+}
+
+fn encode_metrics() -> Vec<u8> {
+    let mut buffer = vec![];
+    TextEncoder::new()
+        .encode(&prometheus::gather(), &mut buffer)
+        .expect("Failed to encode metrics");
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpStream;
+
+    #[test]
+    fn test_flush_stats() {
+        let stats = FlushStats::new("test_flush_stats");
+        stats.observe(100, Duration::from_millis(20), Duration::from_secs(3));
+        stats.observe(50, Duration::from_millis(10), Duration::from_secs(1));
+        // The metrics are shared by the sink.
+        let same_sink_stats = FlushStats::new("test_flush_stats");
+        assert_eq!(same_sink_stats.num_flushes(), 2);
+        assert_eq!(same_sink_stats.num_rows(), 150);
+        assert_eq!(same_sink_stats.latency.get_sample_count(), 2);
+        assert_eq!(same_sink_stats.latency.get_sample_sum(), 4.0);
+        assert_eq!(FlushStats::new("test_other_sink").num_flushes(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_serve_metrics() {
+        FlushStats::new("test_serve_metrics").observe(
+            7,
+            Duration::from_millis(5),
+            Duration::from_millis(50),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener));
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains(r#"clickhouse_flushed_rows_total{sink="test_serve_metrics"} 7"#));
+        assert!(response
+            .contains(r#"clickhouse_flush_latency_seconds_count{sink="test_serve_metrics"} 1"#));
+    }
+}
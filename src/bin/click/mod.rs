@@ -1,162 +1,338 @@
-mod utils;
+mod contract_code;
+mod metrics;
+mod receipt_parents;
+mod schema;
 
-use clickhouse::{Client, Row};
-use fastnear_primitives::near_primitives::views::{
-    AccessKeyPermissionView, ActionView, ExecutionOutcomeView, ExecutionStatusView,
-    ReceiptEnumView, ReceiptView,
-};
-use serde_repr::{Deserialize_repr, Serialize_repr};
-use std::env;
-pub use utils::extract_rows;
-use utils::*;
-
-use serde::Serialize;
+pub use metrics::start_metrics_server;
 
+use crate::click::metrics::FlushStats;
+use crate::click::receipt_parents::ReceiptParents;
+use crate::extract::{
+    extract_rows, AccessKeyChangeRow, AccountChangeRow, ActionRow, BlockRow, BlockRows, ChunkRow,
+    Column, ContractDeploymentRow, DataReceiptRow, EventRow, ExtractConfig, FtTransferRow,
+    NftTransferRow, ReceiptRow, Table, TransactionRow,
+};
+use clickhouse::{Client, Row};
 use fastnear_primitives::block_with_tx_hash::BlockWithTxHashes;
 use fastnear_primitives::near_primitives::types::BlockHeight;
-use std::convert::TryFrom;
-use std::time::Duration;
-use tokio_retry::{strategy::ExponentialBackoff, Retry};
+use serde::Serialize;
+use std::env;
+use std::time::{Duration, Instant};
+use tokio_retry::{strategy::ExponentialBackoff, RetryIf};
 
 const CLICKHOUSE_TARGET: &str = "clickhouse";
-const EVENT_LOG_PREFIX: &str = "EVENT_JSON:";
-
-#[derive(Copy, Clone, Debug, Serialize_repr, Deserialize_repr, PartialEq)]
-#[repr(u8)]
-pub enum ReceiptStatus {
-    Failure = 1,
-    Success = 2,
-}
-
-#[derive(Copy, Clone, Debug, Serialize_repr, Deserialize_repr, PartialEq)]
-#[repr(u8)]
-pub enum ActionKind {
-    CreateAccount = 1,
-    DeployContract = 2,
-    FunctionCall = 3,
-    Transfer = 4,
-    Stake = 5,
-    AddKey = 6,
-    DeleteKey = 7,
-    DeleteAccount = 8,
-    Delegate = 9,
-}
+const DEFAULT_MAX_FLUSH_LATENCY: Duration = Duration::from_secs(60);
+/// ClickHouse errors that retrying can't fix, because the table doesn't match the rows:
+/// NO_SUCH_COLUMN_IN_TABLE, UNKNOWN_TABLE and UNKNOWN_DATABASE.
+const SCHEMA_ERROR_CODES: &[u32] = &[16, 60, 81];
+/// How often the listen loops should call `ClickDB::flush_if_stale`.
+pub const FLUSH_CHECK_INTERVAL: Duration = Duration::from_secs(1);
 
 #[derive(Row, Serialize)]
-pub struct ActionRow {
+pub struct CheckpointRow {
+    pub sink: String,
     pub block_height: u64,
     pub block_hash: String,
     pub block_timestamp: u64,
-    pub receipt_id: String,
-    pub receipt_index: u16,
-    pub action_index: u8,
-    pub signer_id: String,
-    pub signer_public_key: String,
-    pub predecessor_id: String,
-    pub account_id: String,
-    pub status: ReceiptStatus,
-    pub action: ActionKind,
-    pub contract_hash: Option<String>,
-    pub public_key: Option<String>,
-    pub access_key_contract_id: Option<String>,
-    pub deposit: Option<u128>,
-    pub gas_price: u128,
-    pub attached_gas: Option<u64>,
-    pub gas_burnt: u64,
-    pub tokens_burnt: u128,
-    pub method_name: Option<String>,
-    pub args_account_id: Option<String>,
-    pub args_new_account_id: Option<String>,
-    pub args_owner_id: Option<String>,
-    pub args_receiver_id: Option<String>,
-    pub args_sender_id: Option<String>,
-    pub args_token_id: Option<String>,
-    pub args_amount: Option<u128>,
-    pub args_balance: Option<u128>,
-    pub args_nft_contract_id: Option<String>,
-    pub args_nft_token_id: Option<String>,
-    pub args_utm_source: Option<String>,
-    pub args_utm_medium: Option<String>,
-    pub args_utm_campaign: Option<String>,
-    pub args_utm_term: Option<String>,
-    pub args_utm_content: Option<String>,
-    pub return_value_int: Option<u128>,
 }
 
-#[derive(Row, Serialize)]
-pub struct EventRow {
-    pub block_height: u64,
-    pub block_hash: String,
-    pub block_timestamp: u64,
-    pub receipt_id: String,
-    pub receipt_index: u16,
-    pub log_index: u16,
-    pub signer_id: String,
-    pub signer_public_key: String,
-    pub predecessor_id: String,
-    pub account_id: String,
-    pub status: ReceiptStatus,
-
-    pub version: Option<String>,
-    pub standard: Option<String>,
-    pub event: Option<String>,
-    pub data_account_id: Option<String>,
-    pub data_owner_id: Option<String>,
-    pub data_old_owner_id: Option<String>,
-    pub data_new_owner_id: Option<String>,
-    pub data_liquidation_account_id: Option<String>,
-    pub data_authorized_id: Option<String>,
-    pub data_token_ids: Vec<String>,
-    pub data_token_id: Option<String>,
-    pub data_position: Option<String>,
-    pub data_amount: Option<u128>,
+impl CheckpointRow {
+    pub const TABLE: Table = Table {
+        name: "checkpoints",
+        columns: &[
+            Column::new(
+                "sink",
+                "String",
+                "The name of the indexer that writes the blocks",
+            ),
+            Column::new(
+                "block_height",
+                "UInt64",
+                "The last block height that was fully committed by the sink",
+            ),
+            Column::new(
+                "block_hash",
+                "String",
+                "The last block hash that was fully committed by the sink",
+            ),
+            Column::new(
+                "block_timestamp",
+                "DateTime64(9, 'UTC')",
+                "The last block timestamp in UTC that was fully committed by the sink",
+            ),
+        ],
+        indexes: &[],
+        primary_key: None,
+        order_by: "sink",
+        version_column: Some("block_height"),
+        row_columns: Self::COLUMN_NAMES,
+    };
 }
 
+/// Buffers rows of whole blocks and flushes all tables together, followed by a checkpoint of the
+/// last flushed block for the given sink. A crash between the table inserts and the checkpoint
+/// means the blocks after the checkpoint are replayed on restart and their rows are inserted again.
+/// The duplicates are only collapsed when ClickHouse merges the `ReplacingMergeTree` parts, so
+/// until then they are visible to queries without `FINAL`.
 pub struct ClickDB {
     pub client: Client,
+    pub sink: String,
+    /// Blocks up to this height are skipped by `extract_info`. Set by `resume_after` and by every
+    /// commit.
+    pub last_committed_block_height: Option<BlockHeight>,
+    /// The last block whose rows are buffered, but not yet committed.
+    pub last_block: Option<CheckpointRow>,
     pub actions: Vec<ActionRow>,
     pub events: Vec<EventRow>,
+    pub transactions: Vec<TransactionRow>,
+    pub account_changes: Vec<AccountChangeRow>,
+    pub access_key_changes: Vec<AccessKeyChangeRow>,
+    pub receipts: Vec<ReceiptRow>,
+    pub data_receipts: Vec<DataReceiptRow>,
+    pub blocks: Vec<BlockRow>,
+    pub chunks: Vec<ChunkRow>,
+    pub contract_deployments: Vec<ContractDeploymentRow>,
+    pub ft_transfers: Vec<FtTransferRow>,
+    pub nft_transfers: Vec<NftTransferRow>,
+    /// The parent receipt ID and the block height for recently produced receipts.
+    pub receipt_parents: ReceiptParents,
+    pub extract_config: ExtractConfig,
     pub min_batch: usize,
+    /// The maximum time buffered blocks can wait before being flushed, regardless of `min_batch`.
+    pub max_latency: Duration,
+    /// When the first block of the buffered blocks was buffered. None if nothing is buffered.
+    pub first_buffered_at: Option<Instant>,
+    pub flush_stats: FlushStats,
 }
 
 impl ClickDB {
-    pub fn new(min_batch: usize) -> Self {
+    /// The checkpoints are stored under `sink`, while the flush metrics are labeled with
+    /// `metrics_label`, so sinks created per range can share one label.
+    pub fn new(sink: &str, metrics_label: &str, min_batch: usize) -> Self {
         Self {
             client: establish_connection(),
+            sink: sink.to_string(),
+            last_committed_block_height: None,
+            last_block: None,
             actions: Vec::new(),
             events: Vec::new(),
+            transactions: Vec::new(),
+            account_changes: Vec::new(),
+            access_key_changes: Vec::new(),
+            receipts: Vec::new(),
+            data_receipts: Vec::new(),
+            blocks: Vec::new(),
+            chunks: Vec::new(),
+            contract_deployments: Vec::new(),
+            ft_transfers: Vec::new(),
+            nft_transfers: Vec::new(),
+            receipt_parents: ReceiptParents::default(),
+            extract_config: ExtractConfig::from_env(),
             min_batch,
+            max_latency: env::var("MAX_FLUSH_LATENCY_SEC")
+                .map(|s| Duration::from_secs(s.parse().expect("Invalid MAX_FLUSH_LATENCY_SEC")))
+                .unwrap_or(DEFAULT_MAX_FLUSH_LATENCY),
+            first_buffered_at: None,
+            flush_stats: FlushStats::new(metrics_label),
         }
     }
 
     pub async fn commit(&mut self) -> clickhouse::error::Result<()> {
+        let start = Instant::now();
+        let num_rows = self.num_pending_rows();
+        let block_height = self.last_block.as_ref().map(|b| b.block_height);
         self.commit_actions().await?;
         self.commit_events().await?;
+        self.commit_transactions().await?;
+        self.commit_account_changes().await?;
+        self.commit_access_key_changes().await?;
+        self.commit_receipts().await?;
+        self.commit_data_receipts().await?;
+        self.commit_blocks().await?;
+        self.commit_chunks().await?;
+        self.commit_contract_deployments().await?;
+        self.commit_ft_transfers().await?;
+        self.commit_nft_transfers().await?;
+        self.commit_checkpoint().await?;
+
+        let duration = start.elapsed();
+        if let Some(first_buffered_at) = self.first_buffered_at.take() {
+            self.flush_stats
+                .observe(num_rows, duration, first_buffered_at.elapsed());
+            tracing::log::info!(target: CLICKHOUSE_TARGET, "Flushed {} rows up to #{} in {:?}. Total: {} flushes, {} rows", num_rows, block_height.unwrap_or(0), duration, self.flush_stats.num_flushes(), self.flush_stats.num_rows());
+        }
+        Ok(())
+    }
+
+    /// Returns true if the first buffered block has been waiting longer than `max_latency`.
+    pub fn is_stale(&self) -> bool {
+        self.first_buffered_at
+            .map(|first_buffered_at| first_buffered_at.elapsed() >= self.max_latency)
+            .unwrap_or(false)
+    }
+
+    pub async fn flush_if_stale(&mut self) -> clickhouse::error::Result<()> {
+        if self.is_stale() {
+            self.commit().await?;
+        }
         Ok(())
     }
 
-    pub async fn commit_actions(&mut self) -> clickhouse::error::Result<()> {
+    pub fn num_pending_rows(&self) -> usize {
+        self.actions.len()
+            + self.events.len()
+            + self.transactions.len()
+            + self.account_changes.len()
+            + self.access_key_changes.len()
+            + self.receipts.len()
+            + self.data_receipts.len()
+            + self.blocks.len()
+            + self.chunks.len()
+            + self.contract_deployments.len()
+            + self.ft_transfers.len()
+            + self.nft_transfers.len()
+    }
+
+    async fn commit_checkpoint(&mut self) -> clickhouse::error::Result<()> {
+        if let Some(checkpoint) = self.last_block.take() {
+            let block_height = checkpoint.block_height;
+            insert_rows_with_retry(&self.client, &vec![checkpoint], "checkpoints").await?;
+            self.last_committed_block_height = Some(block_height);
+        }
+        Ok(())
+    }
+
+    async fn commit_actions(&mut self) -> clickhouse::error::Result<()> {
         insert_rows_with_retry(&self.client, &self.actions, "actions").await?;
         self.actions.clear();
         Ok(())
     }
 
-    pub async fn commit_events(&mut self) -> clickhouse::error::Result<()> {
+    async fn commit_events(&mut self) -> clickhouse::error::Result<()> {
         insert_rows_with_retry(&self.client, &self.events, "events").await?;
         self.events.clear();
         Ok(())
     }
 
-    pub async fn last_block_height(&self) -> clickhouse::error::Result<Option<BlockHeight>> {
-        let block_height = self
-            .client
-            .query("SELECT max(block_height) FROM actions")
-            .fetch_one::<u64>()
+    async fn commit_transactions(&mut self) -> clickhouse::error::Result<()> {
+        insert_rows_with_retry(&self.client, &self.transactions, "transactions").await?;
+        self.transactions.clear();
+        Ok(())
+    }
+
+    async fn commit_account_changes(&mut self) -> clickhouse::error::Result<()> {
+        insert_rows_with_retry(&self.client, &self.account_changes, "account_changes").await?;
+        self.account_changes.clear();
+        Ok(())
+    }
+
+    async fn commit_access_key_changes(&mut self) -> clickhouse::error::Result<()> {
+        insert_rows_with_retry(&self.client, &self.access_key_changes, "access_key_changes")
             .await?;
-        Ok(Some(block_height))
+        self.access_key_changes.clear();
+        Ok(())
+    }
+
+    async fn commit_receipts(&mut self) -> clickhouse::error::Result<()> {
+        insert_rows_with_retry(&self.client, &self.receipts, "receipts").await?;
+        self.receipts.clear();
+        Ok(())
+    }
+
+    async fn commit_data_receipts(&mut self) -> clickhouse::error::Result<()> {
+        insert_rows_with_retry(&self.client, &self.data_receipts, "data_receipts").await?;
+        self.data_receipts.clear();
+        Ok(())
+    }
+
+    async fn commit_blocks(&mut self) -> clickhouse::error::Result<()> {
+        insert_rows_with_retry(&self.client, &self.blocks, "blocks").await?;
+        self.blocks.clear();
+        Ok(())
+    }
+
+    async fn commit_chunks(&mut self) -> clickhouse::error::Result<()> {
+        insert_rows_with_retry(&self.client, &self.chunks, "chunks").await?;
+        self.chunks.clear();
+        Ok(())
+    }
+
+    async fn commit_contract_deployments(&mut self) -> clickhouse::error::Result<()> {
+        insert_rows_with_retry(
+            &self.client,
+            &self.contract_deployments,
+            "contract_deployments",
+        )
+        .await?;
+        self.contract_deployments.clear();
+        Ok(())
+    }
+
+    async fn commit_ft_transfers(&mut self) -> clickhouse::error::Result<()> {
+        insert_rows_with_retry(&self.client, &self.ft_transfers, "ft_transfers").await?;
+        self.ft_transfers.clear();
+        Ok(())
+    }
+
+    async fn commit_nft_transfers(&mut self) -> clickhouse::error::Result<()> {
+        insert_rows_with_retry(&self.client, &self.nft_transfers, "nft_transfers").await?;
+        self.nft_transfers.clear();
+        Ok(())
+    }
+
+    /// Sets the parent receipt IDs of the receipts produced by previously seen receipts and
+    /// remembers the receipts produced by the given ones. See `ReceiptParents` for the receipts
+    /// whose parent is unknown.
+    fn fill_parent_receipt_ids(&mut self, receipts: &mut [ReceiptRow], block_height: BlockHeight) {
+        self.receipt_parents.prune(block_height);
+        for receipt in receipts.iter_mut() {
+            receipt.parent_receipt_id = self.receipt_parents.take_parent(&receipt.receipt_id);
+            self.receipt_parents.add_produced(
+                block_height,
+                &receipt.receipt_id,
+                &receipt.produced_receipt_ids,
+            );
+        }
+    }
+
+    /// Creates the missing tables and columns and validates the schema.
+    pub async fn migrate(&self) -> anyhow::Result<()> {
+        schema::migrate(&self.client).await
+    }
+
+    /// Validates that the tables match the rows. Should be called before the ingestion starts.
+    pub async fn validate_schema(&self) -> anyhow::Result<()> {
+        schema::validate_schema(&self.client).await
+    }
+
+    /// Loads the last committed block for the sink, or None if the sink doesn't have a checkpoint.
+    pub async fn load_checkpoint(&self) -> clickhouse::error::Result<Option<BlockHeight>> {
+        self.client
+            .query("SELECT block_height FROM checkpoints WHERE sink = ? ORDER BY block_height DESC LIMIT 1")
+            .bind(&self.sink)
+            .fetch_optional::<u64>()
+            .await
+    }
+
+    /// Skips the blocks up to the given block height, e.g. the loaded checkpoint. Should only be
+    /// called when resuming, because all blocks up to it are dropped by `extract_info`.
+    pub fn resume_after(&mut self, block_height: BlockHeight) {
+        self.last_committed_block_height = Some(block_height);
     }
 }
 
+/// Creates the missing tables and columns if the binary is started with the `migrate` command.
+/// Returns true if it did, so the binary should exit instead of indexing.
+pub async fn migrate_if_requested(db: &ClickDB) -> bool {
+    if env::args().nth(1).as_deref() != Some("migrate") {
+        return false;
+    }
+    db.migrate()
+        .await
+        .expect("Failed to migrate the ClickHouse schema");
+    true
+}
+
 fn establish_connection() -> Client {
     Client::default()
         .with_url(env::var("DATABASE_URL").unwrap())
@@ -167,23 +343,73 @@ fn establish_connection() -> Client {
 
 pub async fn extract_info(db: &mut ClickDB, msg: BlockWithTxHashes) -> anyhow::Result<()> {
     let block_height = msg.block.header.height;
-    let (actions, events) = extract_rows(msg);
+    if db
+        .last_committed_block_height
+        .map(|last_block_height| block_height <= last_block_height)
+        .unwrap_or(false)
+    {
+        tracing::log::debug!(target: CLICKHOUSE_TARGET, "#{}: Skipping already committed block", block_height);
+        return Ok(());
+    }
+    let checkpoint = CheckpointRow {
+        sink: db.sink.clone(),
+        block_height,
+        block_hash: msg.block.header.hash.to_string(),
+        block_timestamp: msg.block.header.timestamp_nanosec,
+    };
+    let BlockRows {
+        actions,
+        events,
+        transactions,
+        account_changes,
+        access_key_changes,
+        mut receipts,
+        data_receipts,
+        blocks,
+        chunks,
+        contract_deployments,
+        ft_transfers,
+        nft_transfers,
+        contract_codes,
+    } = extract_rows(msg, &db.extract_config);
+    if let Some(path) = db.extract_config.contract_code_path.clone() {
+        if !contract_codes.is_empty() {
+            tokio::task::spawn_blocking(move || {
+                for (code_hash, code) in contract_codes {
+                    contract_code::store_contract_code(&path, &code_hash, &code)?;
+                }
+                std::io::Result::Ok(())
+            })
+            .await??;
+        }
+    }
     db.actions.extend(actions);
     db.events.extend(events);
+    db.transactions.extend(transactions);
+    db.account_changes.extend(account_changes);
+    db.access_key_changes.extend(access_key_changes);
+    db.fill_parent_receipt_ids(&mut receipts, block_height);
+    db.receipts.extend(receipts);
+    db.data_receipts.extend(data_receipts);
+    db.blocks.extend(blocks);
+    db.chunks.extend(chunks);
+    db.contract_deployments.extend(contract_deployments);
+    db.ft_transfers.extend(ft_transfers);
+    db.nft_transfers.extend(nft_transfers);
+    db.last_block = Some(checkpoint);
+    db.first_buffered_at.get_or_insert_with(Instant::now);
 
     if block_height % 1000 == 0 {
-        tracing::log::info!(target: CLICKHOUSE_TARGET, "#{}: Having {} actions and {} events", block_height, db.actions.len(), db.events.len());
-    }
-    if db.actions.len() >= db.min_batch {
-        db.commit_actions().await?;
+        tracing::log::info!(target: CLICKHOUSE_TARGET, "#{}: Having {} actions, {} events and {} transactions", block_height, db.actions.len(), db.events.len(), db.transactions.len());
     }
-    if db.events.len() >= db.min_batch {
-        db.commit_events().await?;
+    if db.num_pending_rows() >= db.min_batch || db.is_stale() {
+        db.commit().await?;
     }
     Ok(())
 }
 
-async fn insert_rows_with_retry<T>(
+/// Inserts the rows, retrying until it succeeds unless the table doesn't match the rows.
+pub async fn insert_rows_with_retry<T>(
     client: &Client,
     rows: &Vec<T>,
     table: &str,
@@ -191,23 +417,65 @@ async fn insert_rows_with_retry<T>(
 where
     T: Row + Serialize,
 {
+    if rows.is_empty() {
+        return Ok(());
+    }
     let strategy = ExponentialBackoff::from_millis(100).max_delay(Duration::from_secs(30));
-    let retry_future = Retry::spawn(strategy, || async {
-        let res = || async {
-            let mut insert = client.insert(table)?;
-            for row in rows {
-                insert.write(row).await?;
-            }
-            insert.end().await
-        };
-        match res().await {
-            Ok(_) => Ok(()),
-            Err(err) => {
-                tracing::log::error!(target: CLICKHOUSE_TARGET, "Error inserting rows into \"{}\": {}", table, err);
-                Err(err)
+    let retry_future = RetryIf::spawn(
+        strategy,
+        || async {
+            let res = || async {
+                let mut insert = client.insert(table)?;
+                for row in rows {
+                    insert.write(row).await?;
+                }
+                insert.end().await
+            };
+            match res().await {
+                Ok(_) => Ok(()),
+                Err(err) => {
+                    tracing::log::error!(target: CLICKHOUSE_TARGET, "Error inserting rows into \"{}\": {}", table, err);
+                    Err(err)
+                }
             }
-        }
-    });
+        },
+        |err: &clickhouse::error::Error| !is_schema_error(err),
+    );
 
     retry_future.await
 }
+
+/// Returns true if the error is caused by a table that doesn't match the rows, e.g. a missing table
+/// before `migrate` was run.
+fn is_schema_error(err: &clickhouse::error::Error) -> bool {
+    match err {
+        clickhouse::error::Error::BadResponse(message) => SCHEMA_ERROR_CODES
+            .iter()
+            .any(|code| message.contains(&format!("Code: {}.", code))),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_schema_error() {
+        let bad_response =
+            |message: &str| clickhouse::error::Error::BadResponse(message.to_string());
+        assert!(is_schema_error(&bad_response(
+            "Code: 60. DB::Exception: Table default.blocks does not exist. (UNKNOWN_TABLE)"
+        )));
+        assert!(is_schema_error(&bad_response(
+            "Code: 16. DB::Exception: No such column author_id in table default.chunks. (NO_SUCH_COLUMN_IN_TABLE)"
+        )));
+        assert!(!is_schema_error(&bad_response(
+            "Code: 160. DB::Exception: Estimated query execution time is too long. (TOO_SLOW)"
+        )));
+        assert!(!is_schema_error(&bad_response(
+            "Code: 252. DB::Exception: Too many parts. (TOO_MANY_PARTS)"
+        )));
+        assert!(!is_schema_error(&clickhouse::error::Error::TimedOut));
+    }
+}
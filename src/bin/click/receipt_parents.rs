@@ -0,0 +1,105 @@
+use fastnear_primitives::near_primitives::types::BlockHeight;
+use std::collections::{HashMap, VecDeque};
+
+/// How many blocks to remember the produced receipts for to find their parent receipts.
+const RECEIPT_PARENTS_MAX_AGE: BlockHeight = 1000;
+
+/// Remembers the receipts produced in the last `RECEIPT_PARENTS_MAX_AGE` blocks to find the parent
+/// receipt of the receipts executed later.
+///
+/// The receipts are only kept in memory, so the parent is unknown (NULL) for the receipts whose
+/// parent was executed before a restart, before the first block of a `lake-indexer` range
+/// processed by another worker, or more than `RECEIPT_PARENTS_MAX_AGE` blocks earlier.
+#[derive(Default)]
+pub struct ReceiptParents {
+    /// The parent receipt ID for every produced receipt ID.
+    parents: HashMap<String, String>,
+    /// The produced receipt IDs by the block height, in the order of the block heights.
+    produced: VecDeque<(BlockHeight, Vec<String>)>,
+}
+
+impl ReceiptParents {
+    /// Returns the parent of the given receipt and forgets it, because a receipt is executed once.
+    pub fn take_parent(&mut self, receipt_id: &str) -> Option<String> {
+        self.parents.remove(receipt_id)
+    }
+
+    /// Remembers the receipts produced by the given receipt in the given block. The blocks have to
+    /// be added in the increasing order of the block heights.
+    pub fn add_produced(
+        &mut self,
+        block_height: BlockHeight,
+        receipt_id: &str,
+        produced_receipt_ids: &[String],
+    ) {
+        if produced_receipt_ids.is_empty() {
+            return;
+        }
+        for produced_receipt_id in produced_receipt_ids {
+            self.parents
+                .insert(produced_receipt_id.clone(), receipt_id.to_string());
+        }
+        match self.produced.back_mut() {
+            Some((last_block_height, receipt_ids)) if *last_block_height == block_height => {
+                receipt_ids.extend_from_slice(produced_receipt_ids);
+            }
+            _ => self
+                .produced
+                .push_back((block_height, produced_receipt_ids.to_vec())),
+        }
+    }
+
+    /// Forgets the receipts produced more than `RECEIPT_PARENTS_MAX_AGE` blocks before the given
+    /// block height.
+    pub fn prune(&mut self, block_height: BlockHeight) {
+        while let Some((produced_block_height, _)) = self.produced.front() {
+            if produced_block_height + RECEIPT_PARENTS_MAX_AGE > block_height {
+                break;
+            }
+            let (_, receipt_ids) = self.produced.pop_front().unwrap();
+            for receipt_id in receipt_ids {
+                self.parents.remove(&receipt_id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn test_take_parent() {
+        let mut parents = ReceiptParents::default();
+        parents.add_produced(10, "a", &ids(&["b", "c"]));
+        parents.add_produced(11, "b", &ids(&["d"]));
+        assert_eq!(parents.take_parent("b").as_deref(), Some("a"));
+        assert_eq!(parents.take_parent("b"), None);
+        assert_eq!(parents.take_parent("d").as_deref(), Some("b"));
+        assert_eq!(parents.take_parent("x"), None);
+        assert_eq!(parents.take_parent("c").as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn test_prune_by_block_height() {
+        let mut parents = ReceiptParents::default();
+        parents.add_produced(10, "a", &ids(&["b"]));
+        parents.add_produced(10, "c", &ids(&["d"]));
+        parents.add_produced(500, "e", &ids(&["f", "g"]));
+        parents.prune(10 + RECEIPT_PARENTS_MAX_AGE - 1);
+        assert_eq!(parents.take_parent("b").as_deref(), Some("a"));
+        // Any block height prunes, not only the multiples of the max age.
+        parents.prune(10 + RECEIPT_PARENTS_MAX_AGE);
+        assert_eq!(parents.take_parent("d"), None);
+        assert_eq!(parents.take_parent("f").as_deref(), Some("e"));
+        // A gap in the block heights prunes everything older at once.
+        parents.prune(5000);
+        assert_eq!(parents.take_parent("g"), None);
+        assert!(parents.parents.is_empty());
+        assert!(parents.produced.is_empty());
+    }
+}
@@ -0,0 +1,431 @@
+use crate::click::{CheckpointRow, CLICKHOUSE_TARGET};
+use crate::extract::{
+    AccessKeyChangeRow, AccountChangeRow, ActionRow, BlockRow, ChunkRow, Column,
+    ContractDeploymentRow, DataReceiptRow, EventRow, FtTransferRow, NftTransferRow, ReceiptRow,
+    Table, TransactionRow,
+};
+use clickhouse::Client;
+use std::env;
+
+impl Column {
+    fn definition(&self) -> String {
+        format!(
+            "{} {} COMMENT '{}'",
+            self.name,
+            self.ty,
+            self.comment.replace('\'', "\\'")
+        )
+    }
+}
+
+/// Where to create the tables. With `CLICKHOUSE_CLUSTER`, every table is created as a replicated
+/// `repl_<table>` table on the cluster and a distributed `<table>` table on top of it, otherwise as a
+/// single-node table.
+pub struct SchemaConfig {
+    pub database: String,
+    pub cluster: Option<String>,
+}
+
+impl SchemaConfig {
+    pub fn from_env() -> Self {
+        Self {
+            database: env::var("DATABASE_DATABASE").unwrap(),
+            cluster: env::var("CLICKHOUSE_CLUSTER").ok(),
+        }
+    }
+
+    /// The table that stores the data: the replicated table on the cluster or the table itself.
+    fn storage_table(&self, table: &Table) -> String {
+        match &self.cluster {
+            Some(cluster) => format!(
+                "{}.repl_{} ON CLUSTER {}",
+                self.database, table.name, cluster
+            ),
+            None => table.name.to_string(),
+        }
+    }
+
+    /// The name of the table that stores the data, without the database and the cluster.
+    fn storage_table_name(&self, table: &Table) -> String {
+        match &self.cluster {
+            Some(_) => format!("repl_{}", table.name),
+            None => table.name.to_string(),
+        }
+    }
+
+    /// The distributed table on top of the replicated table, if on a cluster.
+    fn distributed_table(&self, table: &Table) -> Option<String> {
+        self.cluster
+            .as_ref()
+            .map(|cluster| format!("{}.{} ON CLUSTER {}", self.database, table.name, cluster))
+    }
+}
+
+impl Table {
+    fn create_queries(&self, config: &SchemaConfig) -> Vec<String> {
+        let definitions = self
+            .columns
+            .iter()
+            .map(|column| column.definition())
+            .chain(self.indexes.iter().map(|index| format!("INDEX {}", index)))
+            .collect::<Vec<_>>()
+            .join(",\n    ");
+        let engine = match &config.cluster {
+            Some(_) => "ReplicatedReplacingMergeTree",
+            None => "ReplacingMergeTree",
+        };
+        let mut query = format!(
+            "CREATE TABLE IF NOT EXISTS {}\n(\n    {}\n)\nENGINE = {}({})\n",
+            config.storage_table(self),
+            definitions,
+            engine,
+            self.version_column.unwrap_or("")
+        );
+        if let Some(primary_key) = self.primary_key {
+            query.push_str(&format!("PRIMARY KEY ({})\n", primary_key));
+        }
+        query.push_str(&format!("ORDER BY ({})", self.order_by));
+        let mut queries = vec![query];
+        if let Some(cluster) = &config.cluster {
+            queries.push(format!(
+                "CREATE TABLE IF NOT EXISTS {database}.{name} ON CLUSTER {cluster} AS {database}.repl_{name}\nENGINE = Distributed({cluster}, {database}, repl_{name})",
+                name = self.name,
+                database = config.database,
+                cluster = cluster,
+            ));
+        }
+        queries
+    }
+
+    fn modify_column_queries(&self, config: &SchemaConfig, column_index: usize) -> Vec<String> {
+        let column = &self.columns[column_index];
+        let mut queries = vec![format!(
+            "ALTER TABLE {} MODIFY COLUMN {}",
+            config.storage_table(self),
+            column.definition()
+        )];
+        if let Some(distributed_table) = config.distributed_table(self) {
+            queries.push(format!(
+                "ALTER TABLE {} MODIFY COLUMN {}",
+                distributed_table,
+                column.definition()
+            ));
+        }
+        queries
+    }
+
+    fn add_column_queries(&self, config: &SchemaConfig, column_index: usize) -> Vec<String> {
+        let column = &self.columns[column_index];
+        let position = match column_index {
+            0 => "FIRST".to_string(),
+            _ => format!("AFTER {}", self.columns[column_index - 1].name),
+        };
+        let mut queries = vec![format!(
+            "ALTER TABLE {} ADD COLUMN IF NOT EXISTS {} {}",
+            config.storage_table(self),
+            column.definition(),
+            position
+        )];
+        if let Some(distributed_table) = config.distributed_table(self) {
+            queries.push(format!(
+                "ALTER TABLE {} ADD COLUMN IF NOT EXISTS {} {}",
+                distributed_table,
+                column.definition(),
+                position
+            ));
+        }
+        queries
+    }
+}
+
+/// Returns true if the expected type is the existing type made `Nullable`. This is the only change
+/// of an existing column that `migrate` makes.
+fn is_nullable_of(expected_ty: &str, existing_ty: &str) -> bool {
+    normalize_type(expected_ty) == format!("Nullable({})", normalize_type(existing_ty))
+}
+
+/// Returns the existing columns of the table with their types, in the table order.
+async fn table_columns(
+    client: &Client,
+    table: &str,
+) -> clickhouse::error::Result<Vec<(String, String)>> {
+    client
+        .query("SELECT name, type FROM system.columns WHERE database = currentDatabase() AND table = ? ORDER BY position")
+        .bind(table)
+        .fetch_all::<(String, String)>()
+        .await
+}
+
+/// Returns the sorting key of the table, or None if it doesn't exist.
+async fn table_sorting_key(
+    client: &Client,
+    table: &str,
+) -> clickhouse::error::Result<Option<String>> {
+    client
+        .query(
+            "SELECT sorting_key FROM system.tables WHERE database = currentDatabase() AND name = ?",
+        )
+        .bind(table)
+        .fetch_optional::<String>()
+        .await
+}
+
+/// Returns true if the existing sorting key is the `ORDER BY` of the schema. ClickHouse formats the
+/// sorting key without the outer parentheses, so only the whitespace is ignored.
+fn is_same_sorting_key(order_by: &str, sorting_key: &str) -> bool {
+    let normalize = |key: &str| {
+        key.chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>()
+    };
+    normalize(order_by) == normalize(sorting_key)
+}
+
+/// Normalizes the type for comparison, e.g. `Enum8('A' = 1, 'B' = 2)` becomes `Enum('A','B')`.
+fn normalize_type(ty: &str) -> String {
+    let ty = ty
+        .replace(' ', "")
+        .replace("Enum8(", "Enum(")
+        .replace("Enum16(", "Enum(");
+    let mut normalized = String::new();
+    let mut in_quotes = false;
+    let mut chars = ty.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\'' {
+            in_quotes = !in_quotes;
+        }
+        if c == '=' && !in_quotes {
+            while chars
+                .peek()
+                .map(|c| c.is_ascii_digit() || *c == '-')
+                .unwrap_or(false)
+            {
+                chars.next();
+            }
+            continue;
+        }
+        normalized.push(c);
+    }
+    normalized
+}
+
+/// Creates the missing tables and adds the missing columns and indexes to the existing ones.
+/// Existing columns are only modified to become `Nullable`, so the schema is validated afterwards.
+pub async fn migrate(client: &Client) -> anyhow::Result<()> {
+    let config = SchemaConfig::from_env();
+    for table in TABLES {
+        let existing_columns = table_columns(client, table.name).await?;
+        if existing_columns.is_empty() {
+            tracing::log::info!(target: CLICKHOUSE_TARGET, "Creating table {}", table.name);
+            for query in table.create_queries(&config) {
+                client.query(&query).execute().await?;
+            }
+            continue;
+        }
+        for (column_index, column) in table.columns.iter().enumerate() {
+            if let Some((_, ty)) = existing_columns
+                .iter()
+                .find(|(name, _)| name == column.name)
+            {
+                if is_nullable_of(column.ty, ty) {
+                    tracing::log::info!(target: CLICKHOUSE_TARGET, "Making column {}.{} nullable", table.name, column.name);
+                    for query in table.modify_column_queries(&config, column_index) {
+                        client.query(&query).execute().await?;
+                    }
+                }
+                continue;
+            }
+            tracing::log::info!(target: CLICKHOUSE_TARGET, "Adding column {}.{}", table.name, column.name);
+            for query in table.add_column_queries(&config, column_index) {
+                client.query(&query).execute().await?;
+            }
+        }
+        for index in table.indexes {
+            client
+                .query(&format!(
+                    "ALTER TABLE {} ADD INDEX IF NOT EXISTS {}",
+                    config.storage_table(table),
+                    index
+                ))
+                .execute()
+                .await?;
+        }
+    }
+    validate_schema(client).await
+}
+
+/// Checks that every table exists and has the columns of its `Row` struct with the expected
+/// types and the expected sorting key. Extra columns in the tables are allowed.
+pub async fn validate_schema(client: &Client) -> anyhow::Result<()> {
+    let config = SchemaConfig::from_env();
+    let mut errors = vec![];
+    for table in TABLES {
+        let schema_columns = table
+            .columns
+            .iter()
+            .map(|column| column.name)
+            .collect::<Vec<_>>();
+        if schema_columns != table.row_columns {
+            errors.push(format!(
+                "The schema of table {} doesn't match its Row struct",
+                table.name
+            ));
+            continue;
+        }
+        let existing_columns = table_columns(client, table.name).await?;
+        if existing_columns.is_empty() {
+            errors.push(format!("Table {} doesn't exist", table.name));
+            continue;
+        }
+        for column in table.columns {
+            match existing_columns
+                .iter()
+                .find(|(name, _)| name == column.name)
+            {
+                None => errors.push(format!(
+                    "Table {} is missing column {}",
+                    table.name, column.name
+                )),
+                Some((_, ty)) if normalize_type(ty) != normalize_type(column.ty) => {
+                    errors.push(format!(
+                        "Column {}.{} has type {}, expected {}",
+                        table.name, column.name, ty, column.ty
+                    ))
+                }
+                _ => {}
+            }
+        }
+        // The sorting key can't be changed by `migrate`. A `ReplacingMergeTree` deduplicates the
+        // rows by it, so rows that only differ in a newer key column would be merged together.
+        let storage_table_name = config.storage_table_name(table);
+        if let Some(sorting_key) = table_sorting_key(client, &storage_table_name).await? {
+            if !is_same_sorting_key(table.order_by, &sorting_key) {
+                errors.push(format!(
+                    "Table {} is ordered by ({}), expected ({}). The table has to be recreated",
+                    storage_table_name, sorting_key, table.order_by
+                ));
+            }
+        }
+    }
+    if !errors.is_empty() {
+        anyhow::bail!(
+            "ClickHouse schema validation failed. Run `migrate` to create the missing tables and columns:\n{}",
+            errors.join("\n")
+        );
+    }
+    Ok(())
+}
+
+pub const TABLES: &[Table] = &[
+    ActionRow::TABLE,
+    EventRow::TABLE,
+    TransactionRow::TABLE,
+    AccountChangeRow::TABLE,
+    AccessKeyChangeRow::TABLE,
+    ReceiptRow::TABLE,
+    DataReceiptRow::TABLE,
+    BlockRow::TABLE,
+    ChunkRow::TABLE,
+    ContractDeploymentRow::TABLE,
+    FtTransferRow::TABLE,
+    NftTransferRow::TABLE,
+    CheckpointRow::TABLE,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_TABLE: Table = Table {
+        name: "test",
+        columns: &[
+            Column::new("a", "UInt64", "The 'a' column"),
+            Column::new("b", "Nullable(String)", "The b column"),
+        ],
+        indexes: &["b_bloom_index b TYPE bloom_filter() GRANULARITY 1"],
+        primary_key: None,
+        order_by: "a",
+        version_column: None,
+        row_columns: &["a", "b"],
+    };
+
+    fn config(cluster: Option<&str>) -> SchemaConfig {
+        SchemaConfig {
+            database: "db".to_string(),
+            cluster: cluster.map(|cluster| cluster.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_create_queries() {
+        assert_eq!(
+            TEST_TABLE.create_queries(&config(None)),
+            vec![
+                "CREATE TABLE IF NOT EXISTS test\n(\n    a UInt64 COMMENT 'The \\'a\\' column',\n    b Nullable(String) COMMENT 'The b column',\n    INDEX b_bloom_index b TYPE bloom_filter() GRANULARITY 1\n)\nENGINE = ReplacingMergeTree()\nORDER BY (a)"
+            ]
+        );
+        let queries = TEST_TABLE.create_queries(&config(Some("c")));
+        assert_eq!(queries.len(), 2);
+        assert!(queries[0].starts_with("CREATE TABLE IF NOT EXISTS db.repl_test ON CLUSTER c\n"));
+        assert!(queries[0].contains("ENGINE = ReplicatedReplacingMergeTree()"));
+        assert_eq!(
+            queries[1],
+            "CREATE TABLE IF NOT EXISTS db.test ON CLUSTER c AS db.repl_test\nENGINE = Distributed(c, db, repl_test)"
+        );
+    }
+
+    #[test]
+    fn test_alter_queries_on_cluster() {
+        assert_eq!(
+            TEST_TABLE.add_column_queries(&config(Some("c")), 1),
+            vec![
+                "ALTER TABLE db.repl_test ON CLUSTER c ADD COLUMN IF NOT EXISTS b Nullable(String) COMMENT 'The b column' AFTER a",
+                "ALTER TABLE db.test ON CLUSTER c ADD COLUMN IF NOT EXISTS b Nullable(String) COMMENT 'The b column' AFTER a",
+            ]
+        );
+        assert_eq!(
+            TEST_TABLE.modify_column_queries(&config(Some("c")), 1),
+            vec![
+                "ALTER TABLE db.repl_test ON CLUSTER c MODIFY COLUMN b Nullable(String) COMMENT 'The b column'",
+                "ALTER TABLE db.test ON CLUSTER c MODIFY COLUMN b Nullable(String) COMMENT 'The b column'",
+            ]
+        );
+        assert_eq!(
+            TEST_TABLE.add_column_queries(&config(None), 0),
+            vec!["ALTER TABLE test ADD COLUMN IF NOT EXISTS a UInt64 COMMENT 'The \\'a\\' column' FIRST"]
+        );
+    }
+
+    #[test]
+    fn test_is_same_sorting_key() {
+        assert!(is_same_sorting_key(
+            "block_timestamp, account_id, receipt_index, action_index, ifNull(parent_action_index, 255)",
+            "block_timestamp, account_id, receipt_index, action_index, ifNull(parent_action_index, 255)"
+        ));
+        assert!(is_same_sorting_key(
+            "block_height,shard_id",
+            "block_height, shard_id"
+        ));
+        assert!(!is_same_sorting_key(
+            "block_timestamp, account_id, receipt_index, action_index, ifNull(parent_action_index, 255)",
+            "block_timestamp, account_id, receipt_index, action_index"
+        ));
+        assert_eq!(
+            config(Some("c")).storage_table_name(&TEST_TABLE),
+            "repl_test"
+        );
+        assert_eq!(config(None).storage_table_name(&TEST_TABLE), "test");
+    }
+
+    #[test]
+    fn test_tables_match_rows() {
+        for table in TABLES {
+            let columns = table
+                .columns
+                .iter()
+                .map(|column| column.name)
+                .collect::<Vec<_>>();
+            assert_eq!(columns, table.row_columns, "{}", table.name);
+        }
+    }
+}
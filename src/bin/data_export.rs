@@ -1,5 +1,6 @@
 mod click;
 mod common;
+mod extract;
 mod redis_db;
 mod rpc;
 
@@ -1,5 +1,8 @@
-mod click;
 mod common;
+// Only the actions and events are used here. The other rows and the table schemas are only used
+// by the ClickHouse indexers.
+#[allow(dead_code)]
+mod extract;
 mod redis_db;
 
 use redis_db::RedisDB;
@@ -7,7 +10,7 @@ use std::collections::{HashMap, HashSet};
 use std::env;
 use std::str::FromStr;
 
-use crate::click::{extract_rows, ActionKind, ActionRow, EventRow, ReceiptStatus};
+use crate::extract::{extract_rows, ActionKind, ActionRow, EventRow, ReceiptStatus};
 use dotenv::dotenv;
 use near_crypto::PublicKey;
 use near_indexer::near_primitives::types::{AccountId, BlockHeight};
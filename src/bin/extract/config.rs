@@ -0,0 +1,73 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+
+const ANY_EVENT: &str = "*";
+
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EventFieldKind {
+    /// Stored in `data_extra_strings`. Non-string JSON values are stored as JSON.
+    String,
+    /// Stored in `data_extra_amounts`. Parsed from a decimal string or a JSON number.
+    Amount,
+}
+
+/// Data fields to extract per event: `standard` -> `event` (or `*` for every event of the
+/// standard) -> data field name -> kind.
+pub type EventFieldsConfig = BTreeMap<String, BTreeMap<String, BTreeMap<String, EventFieldKind>>>;
+
+#[derive(Default)]
+pub struct ExtractConfig {
+    /// Max length of the raw `data_json` of an event. If not set, `data_json` isn't stored.
+    pub event_data_json_max_length: Option<usize>,
+    pub event_fields: EventFieldsConfig,
+    /// Max length of the `args_json` of a function call. If not set, `args_json` isn't stored.
+    pub args_json_max_length: Option<usize>,
+    /// Max length of the `return_value_json` of a receipt. If not set, `return_value_json` isn't
+    /// stored.
+    pub return_value_json_max_length: Option<usize>,
+    /// Folder to store the deployed contract code as `<code_hash>.wasm`. If not set, the code isn't
+    /// kept in `BlockRows::contract_codes`.
+    pub contract_code_path: Option<String>,
+}
+
+impl ExtractConfig {
+    pub fn from_env() -> Self {
+        let event_fields = env::var("EVENT_FIELDS_CONFIG")
+            .map(|path| {
+                let f = std::fs::File::open(path).expect("Failed to open EVENT_FIELDS_CONFIG");
+                serde_json::from_reader(f).expect("Failed to parse EVENT_FIELDS_CONFIG")
+            })
+            .unwrap_or_default();
+        Self {
+            event_data_json_max_length: env::var("EVENT_DATA_JSON_MAX_LENGTH")
+                .ok()
+                .map(|s| s.parse().expect("Invalid EVENT_DATA_JSON_MAX_LENGTH")),
+            event_fields,
+            args_json_max_length: env::var("ARGS_JSON_MAX_LENGTH")
+                .ok()
+                .map(|s| s.parse().expect("Invalid ARGS_JSON_MAX_LENGTH")),
+            return_value_json_max_length: env::var("RETURN_VALUE_JSON_MAX_LENGTH")
+                .ok()
+                .map(|s| s.parse().expect("Invalid RETURN_VALUE_JSON_MAX_LENGTH")),
+            contract_code_path: env::var("CONTRACT_CODE_PATH").ok(),
+        }
+    }
+
+    /// Returns the configured data fields for the given event.
+    pub fn event_fields(
+        &self,
+        standard: Option<&str>,
+        event: Option<&str>,
+    ) -> Vec<(&String, EventFieldKind)> {
+        let Some(events) = standard.and_then(|standard| self.event_fields.get(standard)) else {
+            return vec![];
+        };
+        events
+            .iter()
+            .filter(|(name, _)| name.as_str() == ANY_EVENT || Some(name.as_str()) == event)
+            .flat_map(|(_, fields)| fields.iter().map(|(field, kind)| (field, *kind)))
+            .collect()
+    }
+}
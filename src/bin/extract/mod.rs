@@ -0,0 +1,1670 @@
+mod config;
+mod table;
+mod utils;
+
+use clickhouse::Row;
+pub use config::ExtractConfig;
+use serde::Serialize;
+use serde_repr::{Deserialize_repr, Serialize_repr};
+pub use table::{Column, Table};
+pub use utils::extract_rows;
+
+const EVENT_LOG_PREFIX: &str = "EVENT_JSON:";
+
+#[derive(Copy, Clone, Debug, Serialize_repr, Deserialize_repr, PartialEq)]
+#[repr(u8)]
+pub enum ReceiptStatus {
+    Failure = 1,
+    Success = 2,
+}
+
+#[derive(Copy, Clone, Debug, Serialize_repr, Deserialize_repr, PartialEq)]
+#[repr(u8)]
+pub enum ActionKind {
+    CreateAccount = 1,
+    DeployContract = 2,
+    FunctionCall = 3,
+    Transfer = 4,
+    Stake = 5,
+    AddKey = 6,
+    DeleteKey = 7,
+    DeleteAccount = 8,
+    Delegate = 9,
+}
+
+#[derive(Copy, Clone, Debug, Serialize_repr, Deserialize_repr, PartialEq)]
+#[repr(u8)]
+pub enum StateChangeCauseKind {
+    NotWritableToDisk = 1,
+    InitialState = 2,
+    TransactionProcessing = 3,
+    ActionReceiptProcessingStarted = 4,
+    ActionReceiptGasReward = 5,
+    ReceiptProcessing = 6,
+    PostponedReceipt = 7,
+    UpdatedDelayedReceipts = 8,
+    ValidatorAccountsUpdate = 9,
+    Migration = 10,
+    Resharding = 11,
+}
+
+#[derive(Copy, Clone, Debug, Serialize_repr, Deserialize_repr, PartialEq)]
+#[repr(u8)]
+pub enum StateChangeKind {
+    Update = 1,
+    Deletion = 2,
+}
+
+#[derive(Copy, Clone, Debug, Serialize_repr, Deserialize_repr, PartialEq)]
+#[repr(u8)]
+pub enum AccessKeyPermissionKind {
+    FullAccess = 1,
+    FunctionCall = 2,
+}
+
+#[derive(Copy, Clone, Debug, Serialize_repr, Deserialize_repr, PartialEq)]
+#[repr(u8)]
+pub enum FtTransferKind {
+    Transfer = 1,
+    Mint = 2,
+    Burn = 3,
+    Refund = 4,
+}
+
+#[derive(Copy, Clone, Debug, Serialize_repr, Deserialize_repr, PartialEq)]
+#[repr(u8)]
+pub enum FtTransferSource {
+    Event = 1,
+    FunctionCall = 2,
+}
+
+#[derive(Copy, Clone, Debug, Serialize_repr, Deserialize_repr, PartialEq)]
+#[repr(u8)]
+pub enum NftTransferKind {
+    Transfer = 1,
+    Mint = 2,
+    Burn = 3,
+}
+
+#[derive(Copy, Clone, Debug, Serialize_repr, Deserialize_repr, PartialEq)]
+#[repr(u8)]
+pub enum ReturnValueKind {
+    Empty = 1,
+    Json = 2,
+    Binary = 3,
+    ReceiptId = 4,
+}
+
+#[derive(Row, Serialize)]
+pub struct ActionRow {
+    pub block_height: u64,
+    pub block_hash: String,
+    pub block_timestamp: u64,
+    pub transaction_hash: Option<String>,
+    pub receipt_id: String,
+    pub receipt_index: u16,
+    pub action_index: u8,
+    pub parent_action_index: Option<u8>,
+    pub signer_id: String,
+    pub signer_public_key: String,
+    pub predecessor_id: String,
+    pub account_id: String,
+    pub relayer_id: Option<String>,
+    pub is_refund: bool,
+    /// The receipt outcome fields are None for the inner actions of delegate actions.
+    pub status: Option<ReceiptStatus>,
+    pub action: ActionKind,
+    pub contract_hash: Option<String>,
+    pub public_key: Option<String>,
+    pub access_key_contract_id: Option<String>,
+    pub deposit: Option<u128>,
+    pub gas_price: u128,
+    pub attached_gas: Option<u64>,
+    pub gas_burnt: Option<u64>,
+    pub tokens_burnt: Option<u128>,
+    pub method_name: Option<String>,
+    pub args_account_id: Option<String>,
+    pub args_new_account_id: Option<String>,
+    pub args_owner_id: Option<String>,
+    pub args_receiver_id: Option<String>,
+    pub args_sender_id: Option<String>,
+    pub args_token_id: Option<String>,
+    pub args_amount: Option<u128>,
+    pub args_balance: Option<u128>,
+    pub args_nft_contract_id: Option<String>,
+    pub args_nft_token_id: Option<String>,
+    pub args_utm_source: Option<String>,
+    pub args_utm_medium: Option<String>,
+    pub args_utm_campaign: Option<String>,
+    pub args_utm_term: Option<String>,
+    pub args_utm_content: Option<String>,
+    pub args_json: Option<String>,
+    /// None if the action is not a function call or `ARGS_JSON_MAX_LENGTH` is not set.
+    pub args_is_json: Option<bool>,
+    pub return_value_int: Option<u128>,
+    pub return_value_json: Option<String>,
+    pub return_value_kind: Option<ReturnValueKind>,
+}
+
+impl ActionRow {
+    pub const TABLE: Table = Table {
+        name: "actions",
+        columns: &[
+            Column::new(
+                "block_height",
+                "UInt64",
+                "Block height",
+            ),
+            Column::new(
+                "block_hash",
+                "String",
+                "Block hash",
+            ),
+            Column::new(
+                "block_timestamp",
+                "DateTime64(9, 'UTC')",
+                "Block timestamp in UTC",
+            ),
+            Column::new(
+                "transaction_hash",
+                "Nullable(String)",
+                "Hash of the transaction that originated the receipt",
+            ),
+            Column::new(
+                "receipt_id",
+                "String",
+                "Receipt hash",
+            ),
+            Column::new(
+                "receipt_index",
+                "UInt16",
+                "Index of the receipt that appears in the block across all shards",
+            ),
+            Column::new(
+                "action_index",
+                "UInt8",
+                "Index of the actions within the receipt, or within the DELEGATE action for its inner actions",
+            ),
+            Column::new(
+                "parent_action_index",
+                "Nullable(UInt8)",
+                "Index of the DELEGATE action within the receipt if this is one of its inner actions",
+            ),
+            Column::new(
+                "signer_id",
+                "String",
+                "The account ID of the transaction signer, or the sender of the DELEGATE action for its inner actions",
+            ),
+            Column::new(
+                "signer_public_key",
+                "String",
+                "The public key of the transaction signer, or of the sender of the DELEGATE action for its inner actions",
+            ),
+            Column::new(
+                "predecessor_id",
+                "String",
+                "The account ID of the receipt predecessor, or the sender of the DELEGATE action for its inner actions",
+            ),
+            Column::new(
+                "account_id",
+                "String",
+                "The account ID of where the receipt is executed, or the receiver of the DELEGATE action for its inner actions",
+            ),
+            Column::new(
+                "relayer_id",
+                "Nullable(String)",
+                "The account ID of the relayer (the transaction signer) if the action is DELEGATE or one of its inner actions",
+            ),
+            Column::new(
+                "is_refund",
+                "Bool",
+                "Whether the action is a gas or deposit refund, i.e. the receipt predecessor is `system`",
+            ),
+            Column::new(
+                "status",
+                "Nullable(Enum('FAILURE', 'SUCCESS'))",
+                "The status of the receipt execution, either SUCCESS or FAILURE. NULL for the inner actions of DELEGATE actions",
+            ),
+            Column::new(
+                "action",
+                "Enum('CREATE_ACCOUNT', 'DEPLOY_CONTRACT', 'FUNCTION_CALL', 'TRANSFER', 'STAKE', 'ADD_KEY', 'DELETE_KEY', 'DELETE_ACCOUNT', 'DELEGATE')",
+                "The action type",
+            ),
+            Column::new(
+                "contract_hash",
+                "Nullable(String)",
+                "The hash of the contract if the action is DEPLOY_CONTRACT",
+            ),
+            Column::new(
+                "public_key",
+                "Nullable(String)",
+                "The public key used in the action if the action is ADD_KEY, DELETE_KEY or DELEGATE",
+            ),
+            Column::new(
+                "access_key_contract_id",
+                "Nullable(String)",
+                "The contract ID of the limited access key if the action is ADD_KEY and not a full access key",
+            ),
+            Column::new(
+                "deposit",
+                "Nullable(UInt128)",
+                "The amount of attached deposit in yoctoNEAR if the action is FUNCTION_CALL, STAKE or TRANSFER",
+            ),
+            Column::new(
+                "gas_price",
+                "UInt128",
+                "The gas price in yoctoNEAR for the receipt",
+            ),
+            Column::new(
+                "attached_gas",
+                "Nullable(UInt64)",
+                "The amount of attached gas if the action is FUNCTION_CALL",
+            ),
+            Column::new(
+                "gas_burnt",
+                "Nullable(UInt64)",
+                "The amount of burnt gas for the execution of the whole receipt. NULL for the inner actions of DELEGATE actions",
+            ),
+            Column::new(
+                "tokens_burnt",
+                "Nullable(UInt128)",
+                "The amount of tokens in yoctoNEAR burnt for the execution of the whole receipt. NULL for the inner actions of DELEGATE actions",
+            ),
+            Column::new(
+                "method_name",
+                "Nullable(String)",
+                "The method name if the action is FUNCTION_CALL",
+            ),
+            Column::new(
+                "args_account_id",
+                "Nullable(String)",
+                "`account_id` argument from the JSON arguments if the action is FUNCTION_CALL",
+            ),
+            Column::new(
+                "args_new_account_id",
+                "Nullable(String)",
+                "`new_account_id` argument from the JSON arguments if the action is FUNCTION_CALL",
+            ),
+            Column::new(
+                "args_owner_id",
+                "Nullable(String)",
+                "`owner_id` argument from the JSON arguments if the action is FUNCTION_CALL",
+            ),
+            Column::new(
+                "args_receiver_id",
+                "Nullable(String)",
+                "`receiver_id` argument from the JSON arguments if the action is FUNCTION_CALL",
+            ),
+            Column::new(
+                "args_sender_id",
+                "Nullable(String)",
+                "`sender_id` argument from the JSON arguments if the action is FUNCTION_CALL",
+            ),
+            Column::new(
+                "args_token_id",
+                "Nullable(String)",
+                "`token_id` argument from the JSON arguments if the action is FUNCTION_CALL",
+            ),
+            Column::new(
+                "args_amount",
+                "Nullable(UInt128)",
+                "`amount` argument from the JSON arguments if the action is FUNCTION_CALL",
+            ),
+            Column::new(
+                "args_balance",
+                "Nullable(UInt128)",
+                "`balance` argument from the JSON arguments if the action is FUNCTION_CALL",
+            ),
+            Column::new(
+                "args_nft_contract_id",
+                "Nullable(String)",
+                "`nft_contract_id` argument from the JSON arguments if the action is FUNCTION_CALL",
+            ),
+            Column::new(
+                "args_nft_token_id",
+                "Nullable(String)",
+                "`nft_token_id` argument from the JSON arguments if the action is FUNCTION_CALL",
+            ),
+            Column::new(
+                "args_utm_source",
+                "Nullable(String)",
+                "`_utm_source` argument from the JSON arguments if the action is FUNCTION_CALL",
+            ),
+            Column::new(
+                "args_utm_medium",
+                "Nullable(String)",
+                "`_utm_medium` argument from the JSON arguments if the action is FUNCTION_CALL",
+            ),
+            Column::new(
+                "args_utm_campaign",
+                "Nullable(String)",
+                "`_utm_campaign` argument from the JSON arguments if the action is FUNCTION_CALL",
+            ),
+            Column::new(
+                "args_utm_term",
+                "Nullable(String)",
+                "`_utm_term` argument from the JSON arguments if the action is FUNCTION_CALL",
+            ),
+            Column::new(
+                "args_utm_content",
+                "Nullable(String)",
+                "`_utm_content` argument from the JSON arguments if the action is FUNCTION_CALL",
+            ),
+            Column::new(
+                "args_json",
+                "Nullable(String)",
+                "The arguments of the FUNCTION_CALL action, if enabled with ARGS_JSON_MAX_LENGTH. Non-JSON arguments are stored as a JSON string with base64",
+            ),
+            Column::new(
+                "args_is_json",
+                "Nullable(Bool)",
+                "Whether the arguments of the FUNCTION_CALL action are valid JSON, if enabled with ARGS_JSON_MAX_LENGTH",
+            ),
+            Column::new(
+                "return_value_int",
+                "Nullable(UInt128)",
+                "The parsed integer string from the returned value of the FUNCTION_CALL action. NULL for the inner actions of DELEGATE actions",
+            ),
+            Column::new(
+                "return_value_json",
+                "Nullable(String)",
+                "The returned value of the receipt, if enabled with RETURN_VALUE_JSON_MAX_LENGTH. Non-JSON values are stored as a JSON string with base64. NULL for the inner actions of DELEGATE actions",
+            ),
+            Column::new(
+                "return_value_kind",
+                "Nullable(Enum('EMPTY', 'JSON', 'BINARY', 'RECEIPT_ID'))",
+                "The kind of the returned value of the receipt. NULL if the receipt failed and for the inner actions of DELEGATE actions",
+            ),
+        ],
+        indexes: &[
+            "block_height_minmax_idx block_height TYPE minmax GRANULARITY 1",
+            "account_id_bloom_index account_id TYPE bloom_filter() GRANULARITY 1",
+            "signer_id_bloom_index signer_id TYPE bloom_filter() GRANULARITY 1",
+            "block_hash_bloom_index block_hash TYPE bloom_filter() GRANULARITY 1",
+            "transaction_hash_bloom_index transaction_hash TYPE bloom_filter() GRANULARITY 1",
+            "receipt_id_bloom_index receipt_id TYPE bloom_filter() GRANULARITY 1",
+            "precise_public_key_bloom_index public_key TYPE bloom_filter(0.001) GRANULARITY 1",
+            "predecessor_id_bloom_index predecessor_id TYPE bloom_filter() GRANULARITY 1",
+            "method_name_index method_name TYPE set(0) GRANULARITY 1",
+            "args_account_id_bloom_index args_account_id TYPE bloom_filter() GRANULARITY 1",
+            "args_new_account_id_bloom_index args_new_account_id TYPE bloom_filter() GRANULARITY 1",
+            "args_owner_id_bloom_index args_owner_id TYPE bloom_filter() GRANULARITY 1",
+            "args_receiver_id_bloom_index args_receiver_id TYPE bloom_filter() GRANULARITY 1",
+            "args_sender_id_bloom_index args_sender_id TYPE bloom_filter() GRANULARITY 1",
+        ],
+        primary_key: Some("block_timestamp, account_id"),
+        order_by: "block_timestamp, account_id, receipt_index, action_index, ifNull(parent_action_index, 255)",
+        version_column: None,
+        row_columns: Self::COLUMN_NAMES,
+    };
+}
+
+#[derive(Row, Serialize)]
+pub struct EventRow {
+    pub block_height: u64,
+    pub block_hash: String,
+    pub block_timestamp: u64,
+    pub transaction_hash: Option<String>,
+    pub receipt_id: String,
+    pub receipt_index: u16,
+    pub log_index: u16,
+    pub data_index: u16,
+    pub signer_id: String,
+    pub signer_public_key: String,
+    pub predecessor_id: String,
+    pub account_id: String,
+    pub status: ReceiptStatus,
+
+    pub version: Option<String>,
+    pub standard: Option<String>,
+    pub event: Option<String>,
+    pub data_account_id: Option<String>,
+    pub data_owner_id: Option<String>,
+    pub data_old_owner_id: Option<String>,
+    pub data_new_owner_id: Option<String>,
+    pub data_liquidation_account_id: Option<String>,
+    pub data_authorized_id: Option<String>,
+    pub data_token_ids: Vec<String>,
+    pub data_token_id: Option<String>,
+    pub data_position: Option<String>,
+    pub data_amount: Option<u128>,
+    pub data_json: Option<String>,
+    pub data_extra_strings: Vec<(String, String)>,
+    pub data_extra_amounts: Vec<(String, u128)>,
+}
+
+impl EventRow {
+    pub const TABLE: Table = Table {
+        name: "events",
+        columns: &[
+            Column::new("block_height", "UInt64", "Block height"),
+            Column::new("block_hash", "String", "Block hash"),
+            Column::new(
+                "block_timestamp",
+                "DateTime64(9, 'UTC')",
+                "Block timestamp in UTC",
+            ),
+            Column::new(
+                "transaction_hash",
+                "Nullable(String)",
+                "Hash of the transaction that originated the receipt",
+            ),
+            Column::new("receipt_id", "String", "Receipt hash"),
+            Column::new(
+                "receipt_index",
+                "UInt16",
+                "Index of the receipt that appears in the block across all shards",
+            ),
+            Column::new("log_index", "UInt16", "Index of the log within the receipt"),
+            Column::new(
+                "data_index",
+                "UInt16",
+                "Index of the data object within the JSON event",
+            ),
+            Column::new(
+                "signer_id",
+                "String",
+                "The account ID of the transaction signer",
+            ),
+            Column::new(
+                "signer_public_key",
+                "String",
+                "The public key of the transaction signer",
+            ),
+            Column::new(
+                "predecessor_id",
+                "String",
+                "The account ID of the receipt predecessor",
+            ),
+            Column::new(
+                "account_id",
+                "String",
+                "The account ID of where the receipt is executed",
+            ),
+            Column::new(
+                "status",
+                "Enum('FAILURE', 'SUCCESS')",
+                "The status of the receipt execution, either SUCCESS or FAILURE",
+            ),
+            Column::new(
+                "version",
+                "Nullable(String)",
+                "`version` field from the JSON event",
+            ),
+            Column::new(
+                "standard",
+                "Nullable(String)",
+                "`standard` field from the JSON event",
+            ),
+            Column::new(
+                "event",
+                "Nullable(String)",
+                "`event` field from the JSON event",
+            ),
+            Column::new(
+                "data_account_id",
+                "Nullable(String)",
+                "`account_id` field from the data object in the JSON event",
+            ),
+            Column::new(
+                "data_owner_id",
+                "Nullable(String)",
+                "`owner_id` field from the data object in the JSON event",
+            ),
+            Column::new(
+                "data_old_owner_id",
+                "Nullable(String)",
+                "`old_owner_id` field from the data object in the JSON event",
+            ),
+            Column::new(
+                "data_new_owner_id",
+                "Nullable(String)",
+                "`new_owner_id` field from the data object in the JSON event",
+            ),
+            Column::new(
+                "data_liquidation_account_id",
+                "Nullable(String)",
+                "`liquidation_account_id` field from the data object in the JSON event",
+            ),
+            Column::new(
+                "data_authorized_id",
+                "Nullable(String)",
+                "`authorized_id` field from the data object in the JSON event",
+            ),
+            Column::new(
+                "data_token_ids",
+                "Array(String)",
+                "`token_ids` field from the data object in the JSON event",
+            ),
+            Column::new(
+                "data_token_id",
+                "Nullable(String)",
+                "`token_id` field from the data object in the JSON event",
+            ),
+            Column::new(
+                "data_position",
+                "Nullable(String)",
+                "`position` field from the data object in the JSON event",
+            ),
+            Column::new(
+                "data_amount",
+                "Nullable(UInt128)",
+                "`amount` field from the data object in the JSON event",
+            ),
+            Column::new(
+                "data_json",
+                "Nullable(String)",
+                "The raw JSON of the data object, if enabled with EVENT_DATA_JSON_MAX_LENGTH",
+            ),
+            Column::new(
+                "data_extra_strings",
+                "Map(String, String)",
+                "Data fields configured in EVENT_FIELDS_CONFIG with the `string` kind",
+            ),
+            Column::new(
+                "data_extra_amounts",
+                "Map(String, UInt128)",
+                "Data fields configured in EVENT_FIELDS_CONFIG with the `amount` kind",
+            ),
+        ],
+        indexes: &[
+            "block_height_minmax_idx block_height TYPE minmax GRANULARITY 1",
+            "account_id_bloom_index account_id TYPE bloom_filter() GRANULARITY 1",
+            "event_set_index event TYPE set(0) GRANULARITY 1",
+            "transaction_hash_bloom_index transaction_hash TYPE bloom_filter() GRANULARITY 1",
+            "data_account_id_bloom_index data_account_id TYPE bloom_filter() GRANULARITY 1",
+            "data_owner_id_bloom_index data_owner_id TYPE bloom_filter() GRANULARITY 1",
+            "data_old_owner_id_bloom_index data_old_owner_id TYPE bloom_filter() GRANULARITY 1",
+            "data_new_owner_id_bloom_index data_new_owner_id TYPE bloom_filter() GRANULARITY 1",
+        ],
+        primary_key: Some("block_timestamp, account_id"),
+        order_by: "block_timestamp, account_id, receipt_index, log_index, data_index",
+        version_column: None,
+        row_columns: Self::COLUMN_NAMES,
+    };
+}
+
+#[derive(Row, Serialize)]
+pub struct TransactionRow {
+    pub block_height: u64,
+    pub block_hash: String,
+    pub block_timestamp: u64,
+    pub transaction_hash: String,
+    pub transaction_index: u16,
+    pub shard_id: u64,
+    pub signer_id: String,
+    pub signer_public_key: String,
+    pub nonce: u64,
+    pub receiver_id: String,
+    pub num_actions: u16,
+    pub status: ReceiptStatus,
+    pub converted_into_receipt_id: Option<String>,
+    pub gas_burnt: u64,
+    pub tokens_burnt: u128,
+}
+
+impl TransactionRow {
+    pub const TABLE: Table = Table {
+        name: "transactions",
+        columns: &[
+            Column::new(
+                "block_height",
+                "UInt64",
+                "Block height",
+            ),
+            Column::new(
+                "block_hash",
+                "String",
+                "Block hash",
+            ),
+            Column::new(
+                "block_timestamp",
+                "DateTime64(9, 'UTC')",
+                "Block timestamp in UTC",
+            ),
+            Column::new(
+                "transaction_hash",
+                "String",
+                "Transaction hash",
+            ),
+            Column::new(
+                "transaction_index",
+                "UInt16",
+                "Index of the transaction that appears in the block across all shards",
+            ),
+            Column::new(
+                "shard_id",
+                "UInt64",
+                "The shard ID of the chunk that included the transaction",
+            ),
+            Column::new(
+                "signer_id",
+                "String",
+                "The account ID of the transaction signer",
+            ),
+            Column::new(
+                "signer_public_key",
+                "String",
+                "The public key of the transaction signer",
+            ),
+            Column::new(
+                "nonce",
+                "UInt64",
+                "The transaction nonce",
+            ),
+            Column::new(
+                "receiver_id",
+                "String",
+                "The account ID of the transaction receiver",
+            ),
+            Column::new(
+                "num_actions",
+                "UInt16",
+                "The number of actions in the transaction",
+            ),
+            Column::new(
+                "status",
+                "Enum('FAILURE', 'SUCCESS')",
+                "The status of the transaction conversion, either SUCCESS or FAILURE",
+            ),
+            Column::new(
+                "converted_into_receipt_id",
+                "Nullable(String)",
+                "The ID of the receipt the transaction was converted into",
+            ),
+            Column::new(
+                "gas_burnt",
+                "UInt64",
+                "The amount of burnt gas for the conversion of the transaction into a receipt",
+            ),
+            Column::new(
+                "tokens_burnt",
+                "UInt128",
+                "The amount of tokens in yoctoNEAR burnt for the conversion of the transaction into a receipt",
+            ),
+        ],
+        indexes: &[
+            "block_height_minmax_idx block_height TYPE minmax GRANULARITY 1",
+            "transaction_hash_bloom_index transaction_hash TYPE bloom_filter() GRANULARITY 1",
+            "receiver_id_bloom_index receiver_id TYPE bloom_filter() GRANULARITY 1",
+            "signer_public_key_bloom_index signer_public_key TYPE bloom_filter() GRANULARITY 1",
+        ],
+        primary_key: Some("block_timestamp, signer_id"),
+        order_by: "block_timestamp, signer_id, transaction_index",
+        version_column: None,
+        row_columns: Self::COLUMN_NAMES,
+    };
+}
+
+#[derive(Row, Serialize)]
+pub struct AccountChangeRow {
+    pub block_height: u64,
+    pub block_hash: String,
+    pub block_timestamp: u64,
+    pub shard_id: u64,
+    pub state_change_index: u32,
+    pub account_id: String,
+    pub cause: StateChangeCauseKind,
+    pub cause_transaction_hash: Option<String>,
+    pub cause_receipt_id: Option<String>,
+    pub change: StateChangeKind,
+    pub amount: Option<u128>,
+    pub locked: Option<u128>,
+    pub code_hash: Option<String>,
+    pub storage_usage: Option<u64>,
+}
+
+impl AccountChangeRow {
+    pub const TABLE: Table = Table {
+        name: "account_changes",
+        columns: &[
+            Column::new(
+                "block_height",
+                "UInt64",
+                "Block height",
+            ),
+            Column::new(
+                "block_hash",
+                "String",
+                "Block hash",
+            ),
+            Column::new(
+                "block_timestamp",
+                "DateTime64(9, 'UTC')",
+                "Block timestamp in UTC",
+            ),
+            Column::new(
+                "shard_id",
+                "UInt64",
+                "The shard ID of the state change",
+            ),
+            Column::new(
+                "state_change_index",
+                "UInt32",
+                "Index of the state change that appears in the block across all shards",
+            ),
+            Column::new(
+                "account_id",
+                "String",
+                "The account ID of the changed account",
+            ),
+            Column::new(
+                "cause",
+                "Enum('NOT_WRITABLE_TO_DISK', 'INITIAL_STATE', 'TRANSACTION_PROCESSING', 'ACTION_RECEIPT_PROCESSING_STARTED', 'ACTION_RECEIPT_GAS_REWARD', 'RECEIPT_PROCESSING', 'POSTPONED_RECEIPT', 'UPDATED_DELAYED_RECEIPTS', 'VALIDATOR_ACCOUNTS_UPDATE', 'MIGRATION', 'RESHARDING')",
+                "The cause of the state change",
+            ),
+            Column::new(
+                "cause_transaction_hash",
+                "Nullable(String)",
+                "The transaction hash if the cause is TRANSACTION_PROCESSING",
+            ),
+            Column::new(
+                "cause_receipt_id",
+                "Nullable(String)",
+                "The receipt ID if the cause is related to a receipt",
+            ),
+            Column::new(
+                "change",
+                "Enum('UPDATE', 'DELETION')",
+                "Whether the state was updated or deleted",
+            ),
+            Column::new(
+                "amount",
+                "Nullable(UInt128)",
+                "The liquid balance of the account in yoctoNEAR after the UPDATE",
+            ),
+            Column::new(
+                "locked",
+                "Nullable(UInt128)",
+                "The locked (staked) balance of the account in yoctoNEAR after the UPDATE",
+            ),
+            Column::new(
+                "code_hash",
+                "Nullable(String)",
+                "The hash of the contract code of the account after the UPDATE",
+            ),
+            Column::new(
+                "storage_usage",
+                "Nullable(UInt64)",
+                "The storage usage of the account in bytes after the UPDATE",
+            ),
+        ],
+        indexes: &[
+            "block_height_minmax_idx block_height TYPE minmax GRANULARITY 1",
+            "cause_transaction_hash_bloom_index cause_transaction_hash TYPE bloom_filter() GRANULARITY 1",
+            "cause_receipt_id_bloom_index cause_receipt_id TYPE bloom_filter() GRANULARITY 1",
+        ],
+        primary_key: Some("account_id, block_timestamp"),
+        order_by: "account_id, block_timestamp, state_change_index",
+        version_column: None,
+        row_columns: Self::COLUMN_NAMES,
+    };
+}
+
+#[derive(Row, Serialize)]
+pub struct AccessKeyChangeRow {
+    pub block_height: u64,
+    pub block_hash: String,
+    pub block_timestamp: u64,
+    pub shard_id: u64,
+    pub state_change_index: u32,
+    pub account_id: String,
+    pub public_key: String,
+    pub cause: StateChangeCauseKind,
+    pub cause_transaction_hash: Option<String>,
+    pub cause_receipt_id: Option<String>,
+    pub change: StateChangeKind,
+    pub nonce: Option<u64>,
+    pub permission: Option<AccessKeyPermissionKind>,
+    pub access_key_contract_id: Option<String>,
+    pub allowance: Option<u128>,
+    pub method_names: Vec<String>,
+}
+
+impl AccessKeyChangeRow {
+    pub const TABLE: Table = Table {
+        name: "access_key_changes",
+        columns: &[
+            Column::new(
+                "block_height",
+                "UInt64",
+                "Block height",
+            ),
+            Column::new(
+                "block_hash",
+                "String",
+                "Block hash",
+            ),
+            Column::new(
+                "block_timestamp",
+                "DateTime64(9, 'UTC')",
+                "Block timestamp in UTC",
+            ),
+            Column::new(
+                "shard_id",
+                "UInt64",
+                "The shard ID of the state change",
+            ),
+            Column::new(
+                "state_change_index",
+                "UInt32",
+                "Index of the state change that appears in the block across all shards",
+            ),
+            Column::new(
+                "account_id",
+                "String",
+                "The account ID of the access key owner",
+            ),
+            Column::new(
+                "public_key",
+                "String",
+                "The public key of the access key",
+            ),
+            Column::new(
+                "cause",
+                "Enum('NOT_WRITABLE_TO_DISK', 'INITIAL_STATE', 'TRANSACTION_PROCESSING', 'ACTION_RECEIPT_PROCESSING_STARTED', 'ACTION_RECEIPT_GAS_REWARD', 'RECEIPT_PROCESSING', 'POSTPONED_RECEIPT', 'UPDATED_DELAYED_RECEIPTS', 'VALIDATOR_ACCOUNTS_UPDATE', 'MIGRATION', 'RESHARDING')",
+                "The cause of the state change",
+            ),
+            Column::new(
+                "cause_transaction_hash",
+                "Nullable(String)",
+                "The transaction hash if the cause is TRANSACTION_PROCESSING",
+            ),
+            Column::new(
+                "cause_receipt_id",
+                "Nullable(String)",
+                "The receipt ID if the cause is related to a receipt",
+            ),
+            Column::new(
+                "change",
+                "Enum('UPDATE', 'DELETION')",
+                "Whether the state was updated or deleted",
+            ),
+            Column::new(
+                "nonce",
+                "Nullable(UInt64)",
+                "The nonce of the access key after the UPDATE",
+            ),
+            Column::new(
+                "permission",
+                "Nullable(Enum('FULL_ACCESS', 'FUNCTION_CALL'))",
+                "The permission of the access key after the UPDATE",
+            ),
+            Column::new(
+                "access_key_contract_id",
+                "Nullable(String)",
+                "The contract ID of the limited access key if the permission is FUNCTION_CALL",
+            ),
+            Column::new(
+                "allowance",
+                "Nullable(UInt128)",
+                "The remaining allowance in yoctoNEAR of the limited access key if the permission is FUNCTION_CALL",
+            ),
+            Column::new(
+                "method_names",
+                "Array(String)",
+                "The allowed method names of the limited access key if the permission is FUNCTION_CALL",
+            ),
+        ],
+        indexes: &[
+            "block_height_minmax_idx block_height TYPE minmax GRANULARITY 1",
+            "public_key_bloom_index public_key TYPE bloom_filter(0.001) GRANULARITY 1",
+            "cause_transaction_hash_bloom_index cause_transaction_hash TYPE bloom_filter() GRANULARITY 1",
+            "cause_receipt_id_bloom_index cause_receipt_id TYPE bloom_filter() GRANULARITY 1",
+        ],
+        primary_key: Some("account_id, block_timestamp"),
+        order_by: "account_id, block_timestamp, state_change_index",
+        version_column: None,
+        row_columns: Self::COLUMN_NAMES,
+    };
+}
+
+#[derive(Row, Serialize)]
+pub struct ReceiptRow {
+    pub block_height: u64,
+    pub block_hash: String,
+    pub block_timestamp: u64,
+    pub transaction_hash: Option<String>,
+    pub receipt_id: String,
+    pub receipt_index: u16,
+    pub shard_id: u64,
+    pub predecessor_id: String,
+    pub account_id: String,
+    pub executor_id: String,
+    pub parent_receipt_id: Option<String>,
+    pub produced_receipt_ids: Vec<String>,
+    pub status: ReceiptStatus,
+    pub failure_kind: Option<String>,
+    pub failure_action_index: Option<u8>,
+    pub failure_json: Option<String>,
+    pub gas_burnt: u64,
+    pub tokens_burnt: u128,
+}
+
+impl ReceiptRow {
+    pub const TABLE: Table = Table {
+        name: "receipts",
+        columns: &[
+            Column::new(
+                "block_height",
+                "UInt64",
+                "Block height",
+            ),
+            Column::new(
+                "block_hash",
+                "String",
+                "Block hash",
+            ),
+            Column::new(
+                "block_timestamp",
+                "DateTime64(9, 'UTC')",
+                "Block timestamp in UTC",
+            ),
+            Column::new(
+                "transaction_hash",
+                "Nullable(String)",
+                "Hash of the transaction that originated the receipt",
+            ),
+            Column::new(
+                "receipt_id",
+                "String",
+                "Receipt hash",
+            ),
+            Column::new(
+                "receipt_index",
+                "UInt16",
+                "Index of the receipt that appears in the block across all shards",
+            ),
+            Column::new(
+                "shard_id",
+                "UInt64",
+                "The shard ID where the receipt was executed",
+            ),
+            Column::new(
+                "predecessor_id",
+                "String",
+                "The account ID of the receipt predecessor",
+            ),
+            Column::new(
+                "account_id",
+                "String",
+                "The account ID of where the receipt is executed",
+            ),
+            Column::new(
+                "executor_id",
+                "String",
+                "The account ID of the receipt executor",
+            ),
+            Column::new(
+                "parent_receipt_id",
+                "Nullable(String)",
+                "The ID of the receipt that produced this receipt, if the indexer saw it in the last 1000 blocks since its start or since the start of its range",
+            ),
+            Column::new(
+                "produced_receipt_ids",
+                "Array(String)",
+                "The IDs of the receipts produced by the execution of this receipt",
+            ),
+            Column::new(
+                "status",
+                "Enum('FAILURE', 'SUCCESS')",
+                "The status of the receipt execution, either SUCCESS or FAILURE",
+            ),
+            Column::new(
+                "failure_kind",
+                "Nullable(String)",
+                "The kind of the error if the status is FAILURE, e.g. FunctionCallError or AccountDoesNotExist",
+            ),
+            Column::new(
+                "failure_action_index",
+                "Nullable(UInt8)",
+                "The index of the failed action if the status is FAILURE",
+            ),
+            Column::new(
+                "failure_json",
+                "Nullable(String)",
+                "The full error JSON if the status is FAILURE",
+            ),
+            Column::new(
+                "gas_burnt",
+                "UInt64",
+                "The amount of burnt gas for the execution of the receipt",
+            ),
+            Column::new(
+                "tokens_burnt",
+                "UInt128",
+                "The amount of tokens in yoctoNEAR burnt for the execution of the receipt",
+            ),
+        ],
+        indexes: &[
+            "block_height_minmax_idx block_height TYPE minmax GRANULARITY 1",
+            "receipt_id_bloom_index receipt_id TYPE bloom_filter() GRANULARITY 1",
+            "transaction_hash_bloom_index transaction_hash TYPE bloom_filter() GRANULARITY 1",
+            "parent_receipt_id_bloom_index parent_receipt_id TYPE bloom_filter() GRANULARITY 1",
+            "predecessor_id_bloom_index predecessor_id TYPE bloom_filter() GRANULARITY 1",
+            "failure_kind_index failure_kind TYPE set(0) GRANULARITY 1",
+        ],
+        primary_key: Some("block_timestamp, account_id"),
+        order_by: "block_timestamp, account_id, receipt_index",
+        version_column: None,
+        row_columns: Self::COLUMN_NAMES,
+    };
+}
+
+#[derive(Row, Serialize)]
+pub struct DataReceiptRow {
+    pub block_height: u64,
+    pub block_hash: String,
+    pub block_timestamp: u64,
+    pub receipt_id: String,
+    pub data_receipt_index: u16,
+    pub shard_id: u64,
+    pub data_id: String,
+    /// The account that produced the data.
+    pub predecessor_id: String,
+    /// The account that consumes the data in a callback.
+    pub account_id: String,
+    /// The length of the data. None if the promise failed.
+    pub data_length: Option<u64>,
+    pub is_promise_resume: bool,
+}
+
+impl DataReceiptRow {
+    pub const TABLE: Table = Table {
+        name: "data_receipts",
+        columns: &[
+            Column::new("block_height", "UInt64", "Block height"),
+            Column::new("block_hash", "String", "Block hash"),
+            Column::new(
+                "block_timestamp",
+                "DateTime64(9, 'UTC')",
+                "Block timestamp in UTC",
+            ),
+            Column::new("receipt_id", "String", "Data receipt hash"),
+            Column::new(
+                "data_receipt_index",
+                "UInt16",
+                "Index of the data receipt that appears in the block across all shards",
+            ),
+            Column::new(
+                "shard_id",
+                "UInt64",
+                "The shard ID where the data receipt was included",
+            ),
+            Column::new(
+                "data_id",
+                "String",
+                "The data ID that the consuming action receipt waits for",
+            ),
+            Column::new(
+                "predecessor_id",
+                "String",
+                "The account ID that produced the data",
+            ),
+            Column::new(
+                "account_id",
+                "String",
+                "The account ID that consumes the data (the callback receiver)",
+            ),
+            Column::new(
+                "data_length",
+                "Nullable(UInt64)",
+                "The length of the data in bytes. NULL if the promise failed",
+            ),
+            Column::new(
+                "is_promise_resume",
+                "Bool",
+                "Whether the data receipt resumes a yielded promise",
+            ),
+        ],
+        indexes: &[
+            "block_height_minmax_idx block_height TYPE minmax GRANULARITY 1",
+            "receipt_id_bloom_index receipt_id TYPE bloom_filter() GRANULARITY 1",
+            "data_id_bloom_index data_id TYPE bloom_filter() GRANULARITY 1",
+            "predecessor_id_bloom_index predecessor_id TYPE bloom_filter() GRANULARITY 1",
+        ],
+        primary_key: Some("block_timestamp, account_id"),
+        order_by: "block_timestamp, account_id, data_receipt_index",
+        version_column: None,
+        row_columns: Self::COLUMN_NAMES,
+    };
+}
+
+#[derive(Row, Serialize)]
+pub struct BlockRow {
+    pub block_height: u64,
+    pub block_hash: String,
+    pub prev_block_hash: String,
+    pub block_timestamp: u64,
+    pub block_ordinal: Option<u64>,
+    pub epoch_id: String,
+    pub next_epoch_id: String,
+    pub author_id: String,
+    pub gas_price: u128,
+    pub total_supply: u128,
+    pub chunks_included: u64,
+    pub chunk_mask: Vec<bool>,
+    pub latest_protocol_version: u32,
+}
+
+impl BlockRow {
+    pub const TABLE: Table = Table {
+        name: "blocks",
+        columns: &[
+            Column::new("block_height", "UInt64", "Block height"),
+            Column::new("block_hash", "String", "Block hash"),
+            Column::new(
+                "prev_block_hash",
+                "String",
+                "The hash of the previous block",
+            ),
+            Column::new(
+                "block_timestamp",
+                "DateTime64(9, 'UTC')",
+                "Block timestamp in UTC",
+            ),
+            Column::new(
+                "block_ordinal",
+                "Nullable(UInt64)",
+                "The ordinal number of the block (not counting skipped heights)",
+            ),
+            Column::new("epoch_id", "String", "The epoch ID of the block"),
+            Column::new("next_epoch_id", "String", "The epoch ID of the next epoch"),
+            Column::new(
+                "author_id",
+                "String",
+                "The account ID of the block producer",
+            ),
+            Column::new(
+                "gas_price",
+                "UInt128",
+                "The gas price in yoctoNEAR for the block",
+            ),
+            Column::new(
+                "total_supply",
+                "UInt128",
+                "The total supply of NEAR in yoctoNEAR",
+            ),
+            Column::new(
+                "chunks_included",
+                "UInt64",
+                "The number of new chunks included in the block",
+            ),
+            Column::new(
+                "chunk_mask",
+                "Array(Bool)",
+                "Whether the chunk for each shard is new in this block",
+            ),
+            Column::new(
+                "latest_protocol_version",
+                "UInt32",
+                "The latest protocol version supported by the block producer",
+            ),
+        ],
+        indexes: &[
+            "block_timestamp_minmax_idx block_timestamp TYPE minmax GRANULARITY 1",
+            "block_hash_bloom_index block_hash TYPE bloom_filter() GRANULARITY 1",
+            "author_id_bloom_index author_id TYPE bloom_filter() GRANULARITY 1",
+            "epoch_id_bloom_index epoch_id TYPE bloom_filter() GRANULARITY 1",
+        ],
+        primary_key: Some("block_height"),
+        order_by: "block_height",
+        version_column: None,
+        row_columns: Self::COLUMN_NAMES,
+    };
+}
+
+#[derive(Row, Serialize)]
+pub struct ChunkRow {
+    pub block_height: u64,
+    pub block_hash: String,
+    pub block_timestamp: u64,
+    pub chunk_hash: String,
+    pub shard_id: u64,
+    /// The chunk producer. Only known for the chunks included in the block.
+    pub author_id: Option<String>,
+    pub height_created: u64,
+    pub height_included: u64,
+    /// False if the chunk is missing and the header is copied from the previous chunk.
+    pub is_new: bool,
+    pub gas_used: u64,
+    pub gas_limit: u64,
+    pub balance_burnt: u128,
+    pub num_transactions: Option<u32>,
+    pub num_receipts: Option<u32>,
+}
+
+impl ChunkRow {
+    pub const TABLE: Table = Table {
+        name: "chunks",
+        columns: &[
+            Column::new(
+                "block_height",
+                "UInt64",
+                "Block height",
+            ),
+            Column::new(
+                "block_hash",
+                "String",
+                "Block hash",
+            ),
+            Column::new(
+                "block_timestamp",
+                "DateTime64(9, 'UTC')",
+                "Block timestamp in UTC",
+            ),
+            Column::new(
+                "chunk_hash",
+                "String",
+                "Chunk hash",
+            ),
+            Column::new(
+                "shard_id",
+                "UInt64",
+                "The shard ID of the chunk",
+            ),
+            Column::new(
+                "author_id",
+                "Nullable(String)",
+                "The account ID of the chunk producer. NULL if the chunk is not included in the block",
+            ),
+            Column::new(
+                "height_created",
+                "UInt64",
+                "The block height when the chunk was created",
+            ),
+            Column::new(
+                "height_included",
+                "UInt64",
+                "The block height when the chunk was included",
+            ),
+            Column::new(
+                "is_new",
+                "Bool",
+                "Whether the chunk is new in this block. Otherwise the chunk is missing and the header is from the previous chunk",
+            ),
+            Column::new(
+                "gas_used",
+                "UInt64",
+                "The amount of gas used by the chunk",
+            ),
+            Column::new(
+                "gas_limit",
+                "UInt64",
+                "The gas limit of the chunk",
+            ),
+            Column::new(
+                "balance_burnt",
+                "UInt128",
+                "The amount of tokens in yoctoNEAR burnt in the chunk",
+            ),
+            Column::new(
+                "num_transactions",
+                "Nullable(UInt32)",
+                "The number of transactions in the chunk",
+            ),
+            Column::new(
+                "num_receipts",
+                "Nullable(UInt32)",
+                "The number of receipts in the chunk",
+            ),
+        ],
+        indexes: &[
+            "block_timestamp_minmax_idx block_timestamp TYPE minmax GRANULARITY 1",
+            "chunk_hash_bloom_index chunk_hash TYPE bloom_filter() GRANULARITY 1",
+            "author_id_bloom_index author_id TYPE bloom_filter() GRANULARITY 1",
+        ],
+        primary_key: Some("block_height"),
+        order_by: "block_height, shard_id",
+        version_column: None,
+        row_columns: Self::COLUMN_NAMES,
+    };
+}
+
+#[derive(Row, Serialize)]
+pub struct ContractDeploymentRow {
+    pub block_height: u64,
+    pub block_hash: String,
+    pub block_timestamp: u64,
+    pub shard_id: u64,
+    pub state_change_index: u32,
+    pub account_id: String,
+    pub code_hash: String,
+    pub code_size: u64,
+    pub cause: StateChangeCauseKind,
+    pub transaction_hash: Option<String>,
+    pub cause_receipt_id: Option<String>,
+    /// The predecessor of the receipt that deployed the contract.
+    pub predecessor_id: Option<String>,
+}
+
+impl ContractDeploymentRow {
+    pub const TABLE: Table = Table {
+        name: "contract_deployments",
+        columns: &[
+            Column::new(
+                "block_height",
+                "UInt64",
+                "Block height",
+            ),
+            Column::new(
+                "block_hash",
+                "String",
+                "Block hash",
+            ),
+            Column::new(
+                "block_timestamp",
+                "DateTime64(9, 'UTC')",
+                "Block timestamp in UTC",
+            ),
+            Column::new(
+                "shard_id",
+                "UInt64",
+                "The shard ID of the account",
+            ),
+            Column::new(
+                "state_change_index",
+                "UInt32",
+                "Index of the state change that appears in the block across all shards",
+            ),
+            Column::new(
+                "account_id",
+                "String",
+                "The account ID where the contract was deployed",
+            ),
+            Column::new(
+                "code_hash",
+                "String",
+                "The hash of the deployed contract code",
+            ),
+            Column::new(
+                "code_size",
+                "UInt64",
+                "The size of the deployed contract code in bytes",
+            ),
+            Column::new(
+                "cause",
+                "Enum('NOT_WRITABLE_TO_DISK', 'INITIAL_STATE', 'TRANSACTION_PROCESSING', 'ACTION_RECEIPT_PROCESSING_STARTED', 'ACTION_RECEIPT_GAS_REWARD', 'RECEIPT_PROCESSING', 'POSTPONED_RECEIPT', 'UPDATED_DELAYED_RECEIPTS', 'VALIDATOR_ACCOUNTS_UPDATE', 'MIGRATION', 'RESHARDING')",
+                "The cause of the deployment",
+            ),
+            Column::new(
+                "transaction_hash",
+                "Nullable(String)",
+                "Hash of the transaction that originated the deployment receipt",
+            ),
+            Column::new(
+                "cause_receipt_id",
+                "Nullable(String)",
+                "The receipt ID that deployed the contract",
+            ),
+            Column::new(
+                "predecessor_id",
+                "Nullable(String)",
+                "The account ID of the predecessor of the deployment receipt",
+            ),
+        ],
+        indexes: &[
+            "block_height_minmax_idx block_height TYPE minmax GRANULARITY 1",
+            "code_hash_bloom_index code_hash TYPE bloom_filter() GRANULARITY 1",
+            "predecessor_id_bloom_index predecessor_id TYPE bloom_filter() GRANULARITY 1",
+        ],
+        primary_key: Some("account_id, block_timestamp"),
+        order_by: "account_id, block_timestamp, state_change_index",
+        version_column: None,
+        row_columns: Self::COLUMN_NAMES,
+    };
+}
+
+#[derive(Row, Serialize)]
+pub struct FtTransferRow {
+    pub block_height: u64,
+    pub block_hash: String,
+    pub block_timestamp: u64,
+    pub transaction_hash: Option<String>,
+    pub receipt_id: String,
+    pub receipt_index: u16,
+    pub transfer_index: u16,
+    pub predecessor_id: String,
+    pub token_id: String,
+    pub kind: FtTransferKind,
+    pub source: FtTransferSource,
+    /// None for mints.
+    pub from_id: Option<String>,
+    /// None for burns.
+    pub to_id: Option<String>,
+    pub amount: u128,
+    pub memo: Option<String>,
+    pub status: ReceiptStatus,
+}
+
+impl FtTransferRow {
+    pub const TABLE: Table = Table {
+        name: "ft_transfers",
+        columns: &[
+            Column::new(
+                "block_height",
+                "UInt64",
+                "Block height",
+            ),
+            Column::new(
+                "block_hash",
+                "String",
+                "Block hash",
+            ),
+            Column::new(
+                "block_timestamp",
+                "DateTime64(9, 'UTC')",
+                "Block timestamp in UTC",
+            ),
+            Column::new(
+                "transaction_hash",
+                "Nullable(String)",
+                "Hash of the transaction that originated the receipt",
+            ),
+            Column::new(
+                "receipt_id",
+                "String",
+                "Receipt hash",
+            ),
+            Column::new(
+                "receipt_index",
+                "UInt16",
+                "Index of the receipt that appears in the block across all shards",
+            ),
+            Column::new(
+                "transfer_index",
+                "UInt16",
+                "Index of the transfer within the receipt",
+            ),
+            Column::new(
+                "predecessor_id",
+                "String",
+                "The account ID of the receipt predecessor",
+            ),
+            Column::new(
+                "token_id",
+                "String",
+                "The account ID of the fungible token contract",
+            ),
+            Column::new(
+                "kind",
+                "Enum('TRANSFER', 'MINT', 'BURN', 'REFUND')",
+                "The kind of the transfer. REFUND is a transfer back to the sender by ft_resolve_transfer",
+            ),
+            Column::new(
+                "source",
+                "Enum('EVENT', 'FUNCTION_CALL')",
+                "Whether the transfer is from a NEP-141 event or from a legacy ft_transfer/ft_transfer_call/ft_resolve_transfer call",
+            ),
+            Column::new(
+                "from_id",
+                "Nullable(String)",
+                "The account ID of the sender. NULL for MINT",
+            ),
+            Column::new(
+                "to_id",
+                "Nullable(String)",
+                "The account ID of the receiver. NULL for BURN",
+            ),
+            Column::new(
+                "amount",
+                "UInt128",
+                "The amount of tokens transferred",
+            ),
+            Column::new(
+                "memo",
+                "Nullable(String)",
+                "The memo of the transfer",
+            ),
+            Column::new(
+                "status",
+                "Enum('FAILURE', 'SUCCESS')",
+                "The status of the receipt execution, either SUCCESS or FAILURE",
+            ),
+        ],
+        indexes: &[
+            "block_height_minmax_idx block_height TYPE minmax GRANULARITY 1",
+            "transaction_hash_bloom_index transaction_hash TYPE bloom_filter() GRANULARITY 1",
+            "from_id_bloom_index from_id TYPE bloom_filter() GRANULARITY 1",
+            "to_id_bloom_index to_id TYPE bloom_filter() GRANULARITY 1",
+        ],
+        primary_key: Some("token_id, block_timestamp"),
+        order_by: "token_id, block_timestamp, receipt_index, transfer_index",
+        version_column: None,
+        row_columns: Self::COLUMN_NAMES,
+    };
+}
+
+#[derive(Row, Serialize)]
+pub struct NftTransferRow {
+    pub block_height: u64,
+    pub block_hash: String,
+    pub block_timestamp: u64,
+    pub transaction_hash: Option<String>,
+    pub receipt_id: String,
+    pub receipt_index: u16,
+    pub log_index: u16,
+    pub data_index: u16,
+    pub token_index: u32,
+    pub predecessor_id: String,
+    pub contract_id: String,
+    pub kind: NftTransferKind,
+    pub token_id: String,
+    /// None for mints.
+    pub old_owner_id: Option<String>,
+    /// None for burns.
+    pub new_owner_id: Option<String>,
+    pub authorized_id: Option<String>,
+    pub memo: Option<String>,
+    pub status: ReceiptStatus,
+}
+
+impl NftTransferRow {
+    pub const TABLE: Table = Table {
+        name: "nft_transfers",
+        columns: &[
+            Column::new("block_height", "UInt64", "Block height"),
+            Column::new("block_hash", "String", "Block hash"),
+            Column::new(
+                "block_timestamp",
+                "DateTime64(9, 'UTC')",
+                "Block timestamp in UTC",
+            ),
+            Column::new(
+                "transaction_hash",
+                "Nullable(String)",
+                "Hash of the transaction that originated the receipt",
+            ),
+            Column::new("receipt_id", "String", "Receipt hash"),
+            Column::new(
+                "receipt_index",
+                "UInt16",
+                "Index of the receipt that appears in the block across all shards",
+            ),
+            Column::new(
+                "log_index",
+                "UInt16",
+                "Index of the event log within the receipt",
+            ),
+            Column::new(
+                "data_index",
+                "UInt16",
+                "Index of the data object within the event",
+            ),
+            Column::new(
+                "token_index",
+                "UInt32",
+                "Index of the token ID within the data object",
+            ),
+            Column::new(
+                "predecessor_id",
+                "String",
+                "The account ID of the receipt predecessor",
+            ),
+            Column::new(
+                "contract_id",
+                "String",
+                "The account ID of the NFT contract",
+            ),
+            Column::new(
+                "kind",
+                "Enum('TRANSFER', 'MINT', 'BURN')",
+                "The kind of the NEP-171 event",
+            ),
+            Column::new("token_id", "String", "The token ID"),
+            Column::new(
+                "old_owner_id",
+                "Nullable(String)",
+                "The account ID of the previous owner. NULL for MINT",
+            ),
+            Column::new(
+                "new_owner_id",
+                "Nullable(String)",
+                "The account ID of the new owner. NULL for BURN",
+            ),
+            Column::new(
+                "authorized_id",
+                "Nullable(String)",
+                "The account ID approved to transfer or burn the token on behalf of the owner",
+            ),
+            Column::new("memo", "Nullable(String)", "The memo of the event"),
+            Column::new(
+                "status",
+                "Enum('FAILURE', 'SUCCESS')",
+                "The status of the receipt execution, either SUCCESS or FAILURE",
+            ),
+        ],
+        indexes: &[
+            "block_height_minmax_idx block_height TYPE minmax GRANULARITY 1",
+            "transaction_hash_bloom_index transaction_hash TYPE bloom_filter() GRANULARITY 1",
+            "old_owner_id_bloom_index old_owner_id TYPE bloom_filter() GRANULARITY 1",
+            "new_owner_id_bloom_index new_owner_id TYPE bloom_filter() GRANULARITY 1",
+        ],
+        primary_key: Some("contract_id, token_id"),
+        order_by:
+            "contract_id, token_id, block_timestamp, receipt_index, log_index, data_index, token_index",
+        version_column: None,
+        row_columns: Self::COLUMN_NAMES,
+    };
+}
+
+/// The rows of all tables extracted from a block. Not every binary reads every table, e.g.
+/// `ft-red` only needs the actions and events.
+#[derive(Default)]
+pub struct BlockRows {
+    pub actions: Vec<ActionRow>,
+    pub events: Vec<EventRow>,
+    pub transactions: Vec<TransactionRow>,
+    pub account_changes: Vec<AccountChangeRow>,
+    pub access_key_changes: Vec<AccessKeyChangeRow>,
+    pub receipts: Vec<ReceiptRow>,
+    pub data_receipts: Vec<DataReceiptRow>,
+    pub blocks: Vec<BlockRow>,
+    pub chunks: Vec<ChunkRow>,
+    pub contract_deployments: Vec<ContractDeploymentRow>,
+    pub ft_transfers: Vec<FtTransferRow>,
+    pub nft_transfers: Vec<NftTransferRow>,
+    /// The deployed contract code by its code hash. Only kept if `contract_code_path` is set, to be
+    /// stored by the indexer.
+    pub contract_codes: Vec<(String, Vec<u8>)>,
+}
@@ -0,0 +1,25 @@
+/// A column of a ClickHouse table with its type and comment.
+pub struct Column {
+    pub name: &'static str,
+    pub ty: &'static str,
+    pub comment: &'static str,
+}
+
+impl Column {
+    pub const fn new(name: &'static str, ty: &'static str, comment: &'static str) -> Self {
+        Self { name, ty, comment }
+    }
+}
+
+/// The schema of a ClickHouse table. The columns must match the fields of the `Row` struct that
+/// is inserted into the table, in the same order. Every row struct defines its table as `TABLE`.
+pub struct Table {
+    pub name: &'static str,
+    pub columns: &'static [Column],
+    pub indexes: &'static [&'static str],
+    pub primary_key: Option<&'static str>,
+    pub order_by: &'static str,
+    /// The version column of the `ReplacingMergeTree` engine.
+    pub version_column: Option<&'static str>,
+    pub row_columns: &'static [&'static str],
+}
@@ -0,0 +1,1760 @@
+use crate::extract::config::{EventFieldKind, ExtractConfig};
+use crate::extract::{
+    AccessKeyChangeRow, AccessKeyPermissionKind, AccountChangeRow, ActionKind, ActionRow, BlockRow,
+    BlockRows, ChunkRow, ContractDeploymentRow, DataReceiptRow, EventRow, FtTransferKind,
+    FtTransferRow, FtTransferSource, NftTransferKind, NftTransferRow, ReceiptRow, ReceiptStatus,
+    ReturnValueKind, StateChangeCauseKind, StateChangeKind, TransactionRow, EVENT_LOG_PREFIX,
+};
+use base64::Engine;
+use fastnear_primitives::block_with_tx_hash::BlockWithTxHashes;
+use fastnear_primitives::near_indexer_primitives::IndexerTransactionWithOutcome;
+use fastnear_primitives::near_primitives::errors::TxExecutionError;
+use fastnear_primitives::near_primitives::hash::CryptoHash;
+use fastnear_primitives::near_primitives::types::AccountId;
+use fastnear_primitives::near_primitives::views::{
+    AccessKeyPermissionView, ActionView, ExecutionOutcomeView, ExecutionStatusView,
+    ReceiptEnumView, ReceiptView, StateChangeCauseView, StateChangeValueView,
+    StateChangeWithCauseView,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub fn extract_return_value_int(execution_status: ExecutionStatusView) -> Option<u128> {
+    if let ExecutionStatusView::SuccessValue(value) = execution_status {
+        let str_value = serde_json::from_slice::<String>(&value).ok()?;
+        str_value.parse::<u128>().ok()
+    } else {
+        None
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ArgsData {
+    pub account_id: Option<AccountId>,
+    pub args_new_account_id: Option<AccountId>,
+    pub args_owner_id: Option<AccountId>,
+    pub receiver_id: Option<AccountId>,
+    pub sender_id: Option<AccountId>,
+    pub token_id: Option<String>,
+    pub nft_contract_id: Option<AccountId>,
+    pub nft_token_id: Option<String>,
+    pub amount: Option<String>,
+    pub balance: Option<String>,
+    pub memo: Option<String>,
+    // UTM
+    #[serde(rename = "_utm_source")]
+    pub utm_source: Option<String>,
+    #[serde(rename = "_utm_medium")]
+    pub utm_medium: Option<String>,
+    #[serde(rename = "_utm_campaign")]
+    pub utm_campaign: Option<String>,
+    #[serde(rename = "_utm_term")]
+    pub utm_term: Option<String>,
+    #[serde(rename = "_utm_content")]
+    pub utm_content: Option<String>,
+}
+
+const MAX_TOKEN_LENGTH: usize = 64;
+const MAX_TOKEN_IDS_LENGTH: usize = 4;
+const MAX_MEMO_LENGTH: usize = 1024;
+const NEP141_STANDARD: &str = "nep141";
+const NEP171_STANDARD: &str = "nep171";
+
+fn limit_memo_length(s: &mut Option<String>) {
+    if s.as_ref().map(|s| s.len()).unwrap_or(0) > MAX_MEMO_LENGTH {
+        *s = None;
+    }
+}
+
+fn limit_length(s: &mut Option<String>) {
+    if s.as_ref().map(|s| s.len()).unwrap_or(0) > MAX_TOKEN_LENGTH {
+        *s = None;
+    }
+}
+
+pub fn extract_args_data(action: &ActionView) -> Option<ArgsData> {
+    match action {
+        ActionView::FunctionCall { args, .. } => {
+            let mut args_data: ArgsData = serde_json::from_slice(args).ok()?;
+            // If token length is larger than 64 bytes, we remove it.
+            limit_length(&mut args_data.token_id);
+            limit_length(&mut args_data.nft_token_id);
+            limit_length(&mut args_data.utm_source);
+            limit_length(&mut args_data.utm_medium);
+            limit_length(&mut args_data.utm_campaign);
+            limit_length(&mut args_data.utm_term);
+            limit_length(&mut args_data.utm_content);
+            limit_memo_length(&mut args_data.memo);
+            Some(args_data)
+        }
+        _ => None,
+    }
+}
+
+/// Returns the function call args as JSON with a flag whether the args were parsed as JSON.
+/// Non-JSON args are stored as a JSON string with the base64 of the args. The args are only
+/// parsed if `ARGS_JSON_MAX_LENGTH` is set and only stored if they fit into it.
+pub fn extract_args_json(
+    action: &ActionView,
+    config: &ExtractConfig,
+) -> (Option<String>, Option<bool>) {
+    let ActionView::FunctionCall { args, .. } = action else {
+        return (None, None);
+    };
+    let Some(max_length) = config.args_json_max_length else {
+        return (None, None);
+    };
+    let is_json = serde_json::from_slice::<Value>(args).is_ok();
+    (bytes_to_json(args, is_json, max_length), Some(is_json))
+}
+
+/// Returns the return value as JSON with its kind. Non-JSON values are stored as a JSON string
+/// with the base64 of the value. The value is only stored if `RETURN_VALUE_JSON_MAX_LENGTH` is set
+/// and it fits into it.
+pub fn extract_return_value_json(
+    execution_status: &ExecutionStatusView,
+    config: &ExtractConfig,
+) -> (Option<String>, Option<ReturnValueKind>) {
+    match execution_status {
+        ExecutionStatusView::SuccessValue(value) if value.is_empty() => {
+            (None, Some(ReturnValueKind::Empty))
+        }
+        ExecutionStatusView::SuccessValue(value) => {
+            let is_json = serde_json::from_slice::<Value>(value).is_ok();
+            let return_value_json = config
+                .return_value_json_max_length
+                .and_then(|max_length| bytes_to_json(value, is_json, max_length));
+            let kind = if is_json {
+                ReturnValueKind::Json
+            } else {
+                ReturnValueKind::Binary
+            };
+            (return_value_json, Some(kind))
+        }
+        ExecutionStatusView::SuccessReceiptId(_) => (None, Some(ReturnValueKind::ReceiptId)),
+        ExecutionStatusView::Unknown | ExecutionStatusView::Failure(_) => (None, None),
+    }
+}
+
+/// Returns the bytes as a JSON string, or a JSON string with the base64 of the bytes if they are
+/// not JSON. Returns None if the result is longer than the max length or the JSON is not UTF-8.
+fn bytes_to_json(bytes: &[u8], is_json: bool, max_length: usize) -> Option<String> {
+    let json = if is_json {
+        String::from_utf8(bytes.to_vec()).ok()?
+    } else {
+        Value::String(base64::engine::general_purpose::STANDARD.encode(bytes)).to_string()
+    };
+    Some(json).filter(|json| json.len() <= max_length)
+}
+
+#[derive(Deserialize, Default)]
+pub struct EventData {
+    pub account_id: Option<AccountId>,
+    pub owner_id: Option<AccountId>,
+    pub old_owner_id: Option<AccountId>,
+    pub new_owner_id: Option<AccountId>,
+    pub liquidation_account_id: Option<AccountId>,
+    pub authorized_id: Option<AccountId>,
+    pub token_ids: Option<Vec<String>>,
+    pub token_id: Option<String>,
+    pub position: Option<String>,
+    pub amount: Option<String>,
+    pub memo: Option<String>,
+    /// The original JSON of the data object.
+    #[serde(skip)]
+    pub raw: Value,
+}
+
+#[derive(Deserialize)]
+struct RawEvent {
+    version: Option<String>,
+    standard: Option<String>,
+    event: Option<String>,
+    data: Option<Value>,
+}
+
+pub struct Event {
+    pub version: Option<String>,
+    pub standard: Option<String>,
+    pub event: Option<String>,
+    pub data: Option<Vec<EventData>>,
+}
+
+/// Parses the JSON of an `EVENT_JSON:` log. Returns None if it isn't an event JSON object. A data
+/// object whose known fields don't match `EventData`, e.g. with an invalid account ID, is kept with
+/// all of them empty, so only its raw JSON and the configured extra fields are stored.
+pub fn parse_event(event: &str) -> Option<Event> {
+    let RawEvent {
+        version,
+        standard,
+        event,
+        data,
+    } = serde_json::from_str(event).ok()?;
+    let raw_data = match data {
+        Some(Value::Array(data)) => data,
+        Some(data @ Value::Object(_)) => vec![data],
+        _ => vec![],
+    };
+    // Data objects that don't match the known fields are still kept with their raw JSON.
+    let data = raw_data
+        .into_iter()
+        .map(|raw| {
+            let mut data: EventData = serde_json::from_value(raw.clone()).unwrap_or_default();
+            data.raw = raw;
+            data
+        })
+        .collect::<Vec<_>>();
+    let mut event = Event {
+        version,
+        standard,
+        event,
+        data: Some(data),
+    };
+    limit_length(&mut event.version);
+    limit_length(&mut event.standard);
+    limit_length(&mut event.event);
+    // Token IDs and memos are not limited here, so they are kept in full for `nft_transfers`.
+    if event
+        .data
+        .as_ref()
+        .map(|data| data.is_empty())
+        .unwrap_or(true)
+    {
+        event.data = None;
+    }
+    Some(event)
+}
+
+pub fn extract_event_data_json(data: &EventData, config: &ExtractConfig) -> Option<String> {
+    let max_length = config.event_data_json_max_length?;
+    let data_json = data.raw.to_string();
+    if data_json.len() > max_length {
+        None
+    } else {
+        Some(data_json)
+    }
+}
+
+/// The data fields configured for an event, stored in `data_extra_strings` and
+/// `data_extra_amounts`.
+#[derive(Default, Debug, PartialEq)]
+pub struct EventExtraFields {
+    pub strings: Vec<(String, String)>,
+    pub amounts: Vec<(String, u128)>,
+}
+
+/// Extracts the data fields configured for the event into string and amount key-value pairs.
+pub fn extract_event_extra_fields(
+    event: &Event,
+    data: &EventData,
+    config: &ExtractConfig,
+) -> EventExtraFields {
+    let mut strings = vec![];
+    let mut amounts = vec![];
+    for (field, kind) in config.event_fields(event.standard.as_deref(), event.event.as_deref()) {
+        let Some(value) = data.raw.get(field) else {
+            continue;
+        };
+        match kind {
+            EventFieldKind::String => {
+                let value = match value {
+                    Value::String(s) => s.clone(),
+                    _ => value.to_string(),
+                };
+                strings.push((field.clone(), value));
+            }
+            EventFieldKind::Amount => {
+                let amount = match value {
+                    Value::String(s) => s.parse().ok(),
+                    Value::Number(n) => n.as_u64().map(u128::from),
+                    _ => None,
+                };
+                if let Some(amount) = amount {
+                    amounts.push((field.clone(), amount));
+                }
+            }
+        }
+    }
+    EventExtraFields { strings, amounts }
+}
+
+/// Returns the token IDs limited for `EventRow`.
+fn limit_token_ids(token_ids: &Option<Vec<String>>) -> Vec<String> {
+    let mut token_ids = token_ids.clone().unwrap_or_default();
+    token_ids.retain(|s| s.len() <= MAX_TOKEN_LENGTH);
+    if token_ids.len() > MAX_TOKEN_IDS_LENGTH {
+        token_ids.resize(MAX_TOKEN_IDS_LENGTH, "".to_string());
+    }
+    token_ids
+}
+
+/// Returns the kind, the old owner and the new owner from a NEP-171 event data object.
+pub fn extract_event_nft_transfer(
+    event: &Event,
+    data: &EventData,
+) -> Option<(NftTransferKind, Option<String>, Option<String>)> {
+    if event.standard.as_deref() != Some(NEP171_STANDARD) {
+        return None;
+    }
+    let (kind, old_owner_id, new_owner_id) = match event.event.as_deref()? {
+        "nft_transfer" => (
+            NftTransferKind::Transfer,
+            data.old_owner_id.as_ref(),
+            data.new_owner_id.as_ref(),
+        ),
+        "nft_mint" => (NftTransferKind::Mint, None, data.owner_id.as_ref()),
+        "nft_burn" => (NftTransferKind::Burn, data.owner_id.as_ref(), None),
+        _ => return None,
+    };
+    Some((
+        kind,
+        old_owner_id.map(|account_id| account_id.to_string()),
+        new_owner_id.map(|account_id| account_id.to_string()),
+    ))
+}
+
+pub struct FtTransfer {
+    pub kind: FtTransferKind,
+    pub source: FtTransferSource,
+    pub from_id: Option<String>,
+    pub to_id: Option<String>,
+    pub amount: u128,
+    pub memo: Option<String>,
+}
+
+/// Returns the FT transfer from a NEP-141 event data object. Transfers emitted by
+/// `ft_resolve_transfer` are refunds. The memo is dropped if it's longer than `MAX_MEMO_LENGTH`.
+pub fn extract_event_ft_transfer(
+    event: &Event,
+    data: &EventData,
+    method_name: Option<&str>,
+) -> Option<FtTransfer> {
+    if event.standard.as_deref() != Some(NEP141_STANDARD) {
+        return None;
+    }
+    let (kind, from_id, to_id) = match event.event.as_deref()? {
+        "ft_transfer" if method_name == Some("ft_resolve_transfer") => (
+            FtTransferKind::Refund,
+            data.old_owner_id.as_ref(),
+            data.new_owner_id.as_ref(),
+        ),
+        "ft_transfer" => (
+            FtTransferKind::Transfer,
+            data.old_owner_id.as_ref(),
+            data.new_owner_id.as_ref(),
+        ),
+        "ft_mint" => (FtTransferKind::Mint, None, data.owner_id.as_ref()),
+        "ft_burn" => (FtTransferKind::Burn, data.owner_id.as_ref(), None),
+        _ => return None,
+    };
+    Some(FtTransfer {
+        kind,
+        source: FtTransferSource::Event,
+        from_id: from_id.map(|account_id| account_id.to_string()),
+        to_id: to_id.map(|account_id| account_id.to_string()),
+        amount: data.amount.as_ref()?.parse().ok()?,
+        memo: data
+            .memo
+            .clone()
+            .filter(|memo| memo.len() <= MAX_MEMO_LENGTH),
+    })
+}
+
+/// Returns the FT transfer from a legacy `ft_transfer`, `ft_transfer_call` or
+/// `ft_resolve_transfer` call. Should only be used for receipts without NEP-141 events.
+/// For `ft_resolve_transfer`, the refund is the transferred amount minus the used amount returned.
+pub fn extract_call_ft_transfer(
+    method_name: &str,
+    args_data: &ArgsData,
+    predecessor_id: &str,
+    account_id: &str,
+    return_value_int: Option<u128>,
+) -> Option<FtTransfer> {
+    let amount: u128 = args_data.amount.as_ref()?.parse().ok()?;
+    match method_name {
+        "ft_transfer" | "ft_transfer_call" => Some(FtTransfer {
+            kind: FtTransferKind::Transfer,
+            source: FtTransferSource::FunctionCall,
+            from_id: Some(predecessor_id.to_string()),
+            to_id: Some(args_data.receiver_id.as_ref()?.to_string()),
+            amount,
+            memo: args_data.memo.clone(),
+        }),
+        "ft_resolve_transfer" if predecessor_id == account_id => {
+            let refund_amount = amount.checked_sub(return_value_int?)?;
+            if refund_amount == 0 {
+                return None;
+            }
+            Some(FtTransfer {
+                kind: FtTransferKind::Refund,
+                source: FtTransferSource::FunctionCall,
+                from_id: Some(args_data.receiver_id.as_ref()?.to_string()),
+                to_id: Some(args_data.sender_id.as_ref()?.to_string()),
+                amount: refund_amount,
+                memo: None,
+            })
+        }
+        _ => None,
+    }
+}
+
+pub struct ExpandedAction {
+    pub action_index: u8,
+    pub parent_action_index: Option<u8>,
+    pub signer_id: String,
+    pub signer_public_key: String,
+    pub predecessor_id: String,
+    pub account_id: String,
+    pub relayer_id: Option<String>,
+    pub action: ActionView,
+}
+
+/// Returns the receipt actions, each `Delegate` action followed by its inner actions. The inner
+/// actions are attributed to the delegate sender as the signer and the predecessor, and to the
+/// delegate receiver as the account, while the receipt signer is kept as the relayer.
+pub fn expand_actions(
+    actions: Vec<ActionView>,
+    signer_id: String,
+    signer_public_key: String,
+    predecessor_id: &str,
+    account_id: &str,
+) -> Vec<ExpandedAction> {
+    let mut expanded_actions = vec![];
+    for (action_index, action) in actions.into_iter().enumerate() {
+        let action_index = u8::try_from(action_index).expect("Action index overflow");
+        let delegate_action = match &action {
+            ActionView::Delegate {
+                delegate_action, ..
+            } => Some(delegate_action.clone()),
+            _ => None,
+        };
+        expanded_actions.push(ExpandedAction {
+            action_index,
+            parent_action_index: None,
+            signer_id: signer_id.clone(),
+            signer_public_key: signer_public_key.clone(),
+            predecessor_id: predecessor_id.to_string(),
+            account_id: account_id.to_string(),
+            relayer_id: delegate_action.as_ref().map(|_| signer_id.clone()),
+            action,
+        });
+        if let Some(delegate_action) = delegate_action {
+            for (inner_action_index, inner_action) in
+                delegate_action.get_actions().into_iter().enumerate()
+            {
+                expanded_actions.push(ExpandedAction {
+                    action_index: u8::try_from(inner_action_index)
+                        .expect("Inner action index overflow"),
+                    parent_action_index: Some(action_index),
+                    signer_id: delegate_action.sender_id.to_string(),
+                    signer_public_key: delegate_action.public_key.to_string(),
+                    predecessor_id: delegate_action.sender_id.to_string(),
+                    account_id: delegate_action.receiver_id.to_string(),
+                    relayer_id: Some(signer_id.clone()),
+                    action: inner_action.into(),
+                });
+            }
+        }
+    }
+    expanded_actions
+}
+
+/// Returns the name of the enum variant of a value that is serialized by serde as an externally
+/// tagged enum, e.g. `AccountDoesNotExist` for `{"AccountDoesNotExist": {...}}`.
+fn enum_variant_name<T: Serialize>(value: &T) -> Option<String> {
+    match serde_json::to_value(value).ok()? {
+        Value::String(name) => Some(name),
+        Value::Object(map) => map.into_iter().next().map(|(name, _)| name),
+        _ => None,
+    }
+}
+
+/// Returns the failure kind, the index of the failed action and the full error JSON.
+pub fn extract_failure(
+    execution_status: &ExecutionStatusView,
+) -> (Option<String>, Option<u8>, Option<String>) {
+    match execution_status {
+        ExecutionStatusView::Failure(error) => {
+            let (failure_kind, failure_action_index) = match error {
+                TxExecutionError::ActionError(action_error) => (
+                    enum_variant_name(&action_error.kind),
+                    action_error
+                        .index
+                        .and_then(|index| u8::try_from(index).ok()),
+                ),
+                TxExecutionError::InvalidTxError(invalid_tx_error) => {
+                    (enum_variant_name(invalid_tx_error), None)
+                }
+            };
+            (
+                failure_kind,
+                failure_action_index,
+                serde_json::to_string(error).ok(),
+            )
+        }
+        _ => (None, None, None),
+    }
+}
+
+fn execution_status_to_receipt_status(execution_status: &ExecutionStatusView) -> ReceiptStatus {
+    match execution_status {
+        ExecutionStatusView::Unknown => ReceiptStatus::Failure,
+        ExecutionStatusView::Failure(_) => ReceiptStatus::Failure,
+        ExecutionStatusView::SuccessValue(_) => ReceiptStatus::Success,
+        ExecutionStatusView::SuccessReceiptId(_) => ReceiptStatus::Success,
+    }
+}
+
+/// Returns the cause kind with the transaction hash or the receipt ID that caused the change.
+fn extract_state_change_cause(
+    cause: StateChangeCauseView,
+) -> (StateChangeCauseKind, Option<String>, Option<String>) {
+    match cause {
+        StateChangeCauseView::NotWritableToDisk => {
+            (StateChangeCauseKind::NotWritableToDisk, None, None)
+        }
+        StateChangeCauseView::InitialState => (StateChangeCauseKind::InitialState, None, None),
+        StateChangeCauseView::TransactionProcessing { tx_hash } => (
+            StateChangeCauseKind::TransactionProcessing,
+            Some(tx_hash.to_string()),
+            None,
+        ),
+        StateChangeCauseView::ActionReceiptProcessingStarted { receipt_hash } => (
+            StateChangeCauseKind::ActionReceiptProcessingStarted,
+            None,
+            Some(receipt_hash.to_string()),
+        ),
+        StateChangeCauseView::ActionReceiptGasReward { receipt_hash } => (
+            StateChangeCauseKind::ActionReceiptGasReward,
+            None,
+            Some(receipt_hash.to_string()),
+        ),
+        StateChangeCauseView::ReceiptProcessing { receipt_hash } => (
+            StateChangeCauseKind::ReceiptProcessing,
+            None,
+            Some(receipt_hash.to_string()),
+        ),
+        StateChangeCauseView::PostponedReceipt { receipt_hash } => (
+            StateChangeCauseKind::PostponedReceipt,
+            None,
+            Some(receipt_hash.to_string()),
+        ),
+        StateChangeCauseView::UpdatedDelayedReceipts => {
+            (StateChangeCauseKind::UpdatedDelayedReceipts, None, None)
+        }
+        StateChangeCauseView::ValidatorAccountsUpdate => {
+            (StateChangeCauseKind::ValidatorAccountsUpdate, None, None)
+        }
+        StateChangeCauseView::Migration => (StateChangeCauseKind::Migration, None, None),
+        StateChangeCauseView::Resharding => (StateChangeCauseKind::Resharding, None, None),
+    }
+}
+
+pub fn extract_rows(msg: BlockWithTxHashes, config: &ExtractConfig) -> BlockRows {
+    let mut action_rows = vec![];
+    let mut event_rows = vec![];
+    let mut transaction_rows = vec![];
+    let mut account_change_rows = vec![];
+    let mut access_key_change_rows = vec![];
+    let mut receipt_rows = vec![];
+    let mut contract_deployment_rows = vec![];
+    let mut ft_transfer_rows = vec![];
+    let mut nft_transfer_rows = vec![];
+    let mut data_receipt_rows = vec![];
+    let mut contract_codes = vec![];
+
+    let block_height = msg.block.header.height;
+    let block_hash = msg.block.header.hash.to_string();
+    let block_timestamp = msg.block.header.timestamp_nanosec;
+
+    let header = &msg.block.header;
+    let block_rows = vec![BlockRow {
+        block_height,
+        block_hash: block_hash.clone(),
+        prev_block_hash: header.prev_hash.to_string(),
+        block_timestamp,
+        block_ordinal: header.block_ordinal,
+        epoch_id: header.epoch_id.to_string(),
+        next_epoch_id: header.next_epoch_id.to_string(),
+        author_id: msg.block.author.to_string(),
+        gas_price: header.gas_price,
+        total_supply: header.total_supply,
+        chunks_included: header.chunks_included,
+        chunk_mask: header.chunk_mask.clone(),
+        latest_protocol_version: header.latest_protocol_version,
+    }];
+    let chunk_rows = msg
+        .block
+        .chunks
+        .iter()
+        .map(|chunk| {
+            let shard_chunk = msg
+                .shards
+                .iter()
+                .find(|shard| shard.shard_id == chunk.shard_id)
+                .and_then(|shard| shard.chunk.as_ref());
+            ChunkRow {
+                block_height,
+                block_hash: block_hash.clone(),
+                block_timestamp,
+                chunk_hash: chunk.chunk_hash.to_string(),
+                shard_id: chunk.shard_id,
+                author_id: shard_chunk.map(|shard_chunk| shard_chunk.author.to_string()),
+                height_created: chunk.height_created,
+                height_included: chunk.height_included,
+                is_new: chunk.height_included == block_height,
+                gas_used: chunk.gas_used,
+                gas_limit: chunk.gas_limit,
+                balance_burnt: chunk.balance_burnt,
+                num_transactions: shard_chunk.map(|shard_chunk| {
+                    u32::try_from(shard_chunk.transactions.len())
+                        .expect("Transactions length overflow")
+                }),
+                num_receipts: shard_chunk.map(|shard_chunk| {
+                    u32::try_from(shard_chunk.receipts.len()).expect("Receipts length overflow")
+                }),
+            }
+        })
+        .collect();
+
+    let mut transaction_index: u16 = 0;
+    let mut receipt_index: u16 = 0;
+    let mut data_receipt_index: u16 = 0;
+    let mut state_change_index: u32 = 0;
+    for shard in msg.shards {
+        if let Some(chunk) = shard.chunk {
+            for IndexerTransactionWithOutcome {
+                transaction,
+                outcome,
+            } in chunk.transactions
+            {
+                let ExecutionOutcomeView {
+                    status: execution_status,
+                    gas_burnt,
+                    tokens_burnt,
+                    receipt_ids,
+                    ..
+                } = outcome.execution_outcome.outcome;
+                transaction_rows.push(TransactionRow {
+                    block_height,
+                    block_hash: block_hash.clone(),
+                    block_timestamp,
+                    transaction_hash: transaction.hash.to_string(),
+                    transaction_index,
+                    shard_id: shard.shard_id,
+                    signer_id: transaction.signer_id.to_string(),
+                    signer_public_key: transaction.public_key.to_string(),
+                    nonce: transaction.nonce,
+                    receiver_id: transaction.receiver_id.to_string(),
+                    num_actions: u16::try_from(transaction.actions.len())
+                        .expect("Actions length overflow"),
+                    status: execution_status_to_receipt_status(&execution_status),
+                    converted_into_receipt_id: receipt_ids
+                        .first()
+                        .map(|receipt_id| receipt_id.to_string()),
+                    gas_burnt,
+                    tokens_burnt,
+                });
+                transaction_index = transaction_index
+                    .checked_add(1)
+                    .expect("Transaction index overflow");
+            }
+            // Data receipts don't have execution outcomes, so they are taken from the chunk.
+            for receipt in chunk.receipts {
+                if let ReceiptEnumView::Data {
+                    data_id,
+                    data,
+                    is_promise_resume,
+                } = receipt.receipt
+                {
+                    data_receipt_rows.push(DataReceiptRow {
+                        block_height,
+                        block_hash: block_hash.clone(),
+                        block_timestamp,
+                        receipt_id: receipt.receipt_id.to_string(),
+                        data_receipt_index,
+                        shard_id: shard.shard_id,
+                        data_id: data_id.to_string(),
+                        predecessor_id: receipt.predecessor_id.to_string(),
+                        account_id: receipt.receiver_id.to_string(),
+                        data_length: data.map(|data| data.len() as u64),
+                        is_promise_resume,
+                    });
+                    data_receipt_index = data_receipt_index
+                        .checked_add(1)
+                        .expect("Data receipt index overflow");
+                }
+            }
+        }
+        for StateChangeWithCauseView { cause, value } in shard.state_changes {
+            let (cause, cause_transaction_hash, cause_receipt_id) =
+                extract_state_change_cause(cause);
+            match value {
+                StateChangeValueView::AccountUpdate {
+                    account_id,
+                    account,
+                } => {
+                    account_change_rows.push(AccountChangeRow {
+                        block_height,
+                        block_hash: block_hash.clone(),
+                        block_timestamp,
+                        shard_id: shard.shard_id,
+                        state_change_index,
+                        account_id: account_id.to_string(),
+                        cause,
+                        cause_transaction_hash,
+                        cause_receipt_id,
+                        change: StateChangeKind::Update,
+                        amount: Some(account.amount),
+                        locked: Some(account.locked),
+                        code_hash: Some(account.code_hash.to_string()),
+                        storage_usage: Some(account.storage_usage),
+                    });
+                }
+                StateChangeValueView::ContractCodeUpdate { account_id, code } => {
+                    let code_hash = CryptoHash::hash_bytes(&code).to_string();
+                    let cause_receipt = cause_receipt_id.as_ref().and_then(|cause_receipt_id| {
+                        shard.receipt_execution_outcomes.iter().find(|outcome| {
+                            &outcome.receipt.receipt_id.to_string() == cause_receipt_id
+                        })
+                    });
+                    if config.contract_code_path.is_some() {
+                        contract_codes.push((code_hash.clone(), code.clone()));
+                    }
+                    contract_deployment_rows.push(ContractDeploymentRow {
+                        block_height,
+                        block_hash: block_hash.clone(),
+                        block_timestamp,
+                        shard_id: shard.shard_id,
+                        state_change_index,
+                        account_id: account_id.to_string(),
+                        code_hash,
+                        code_size: code.len() as u64,
+                        cause,
+                        transaction_hash: cause_receipt
+                            .and_then(|outcome| outcome.tx_hash.map(|tx_hash| tx_hash.to_string())),
+                        cause_receipt_id,
+                        predecessor_id: cause_receipt
+                            .map(|outcome| outcome.receipt.predecessor_id.to_string()),
+                    });
+                }
+                StateChangeValueView::AccountDeletion { account_id } => {
+                    account_change_rows.push(AccountChangeRow {
+                        block_height,
+                        block_hash: block_hash.clone(),
+                        block_timestamp,
+                        shard_id: shard.shard_id,
+                        state_change_index,
+                        account_id: account_id.to_string(),
+                        cause,
+                        cause_transaction_hash,
+                        cause_receipt_id,
+                        change: StateChangeKind::Deletion,
+                        amount: None,
+                        locked: None,
+                        code_hash: None,
+                        storage_usage: None,
+                    });
+                }
+                StateChangeValueView::AccessKeyUpdate {
+                    account_id,
+                    public_key,
+                    access_key,
+                } => {
+                    let (permission, access_key_contract_id, allowance, method_names) =
+                        match access_key.permission {
+                            AccessKeyPermissionView::FullAccess => {
+                                (AccessKeyPermissionKind::FullAccess, None, None, vec![])
+                            }
+                            AccessKeyPermissionView::FunctionCall {
+                                allowance,
+                                receiver_id,
+                                method_names,
+                            } => (
+                                AccessKeyPermissionKind::FunctionCall,
+                                Some(receiver_id),
+                                allowance,
+                                method_names,
+                            ),
+                        };
+                    access_key_change_rows.push(AccessKeyChangeRow {
+                        block_height,
+                        block_hash: block_hash.clone(),
+                        block_timestamp,
+                        shard_id: shard.shard_id,
+                        state_change_index,
+                        account_id: account_id.to_string(),
+                        public_key: public_key.to_string(),
+                        cause,
+                        cause_transaction_hash,
+                        cause_receipt_id,
+                        change: StateChangeKind::Update,
+                        nonce: Some(access_key.nonce),
+                        permission: Some(permission),
+                        access_key_contract_id,
+                        allowance,
+                        method_names,
+                    });
+                }
+                StateChangeValueView::AccessKeyDeletion {
+                    account_id,
+                    public_key,
+                } => {
+                    access_key_change_rows.push(AccessKeyChangeRow {
+                        block_height,
+                        block_hash: block_hash.clone(),
+                        block_timestamp,
+                        shard_id: shard.shard_id,
+                        state_change_index,
+                        account_id: account_id.to_string(),
+                        public_key: public_key.to_string(),
+                        cause,
+                        cause_transaction_hash,
+                        cause_receipt_id,
+                        change: StateChangeKind::Deletion,
+                        nonce: None,
+                        permission: None,
+                        access_key_contract_id: None,
+                        allowance: None,
+                        method_names: vec![],
+                    });
+                }
+                _ => {}
+            }
+            state_change_index = state_change_index
+                .checked_add(1)
+                .expect("State change index overflow");
+        }
+        for outcome in shard.receipt_execution_outcomes {
+            let transaction_hash = outcome.tx_hash.map(|tx_hash| tx_hash.to_string());
+            let ReceiptView {
+                predecessor_id,
+                receiver_id: account_id,
+                receipt_id,
+                receipt,
+            } = outcome.receipt;
+            // Gas and deposit refunds are issued by the `system` account.
+            let is_refund = predecessor_id.is_system();
+            let predecessor_id = predecessor_id.to_string();
+            let account_id = account_id.to_string();
+            let receipt_id = receipt_id.to_string();
+            let ExecutionOutcomeView {
+                status: execution_status,
+                gas_burnt,
+                tokens_burnt,
+                logs,
+                receipt_ids,
+                executor_id,
+                ..
+            } = outcome.execution_outcome.outcome;
+            let status = execution_status_to_receipt_status(&execution_status);
+            let (failure_kind, failure_action_index, failure_json) =
+                extract_failure(&execution_status);
+            receipt_rows.push(ReceiptRow {
+                block_height,
+                block_hash: block_hash.clone(),
+                block_timestamp,
+                transaction_hash: transaction_hash.clone(),
+                receipt_id: receipt_id.clone(),
+                receipt_index,
+                shard_id: shard.shard_id,
+                predecessor_id: predecessor_id.clone(),
+                account_id: account_id.clone(),
+                executor_id: executor_id.to_string(),
+                parent_receipt_id: None,
+                produced_receipt_ids: receipt_ids
+                    .iter()
+                    .map(|receipt_id| receipt_id.to_string())
+                    .collect(),
+                status,
+                failure_kind,
+                failure_action_index,
+                failure_json,
+                gas_burnt,
+                tokens_burnt,
+            });
+            let (return_value_json, return_value_kind) =
+                extract_return_value_json(&execution_status, config);
+            let return_value_int = extract_return_value_int(execution_status);
+            match receipt {
+                ReceiptEnumView::Action {
+                    signer_id,
+                    signer_public_key,
+                    actions,
+                    gas_price,
+                    ..
+                } => {
+                    let method_name = actions.first().and_then(|action| match action {
+                        ActionView::FunctionCall { method_name, .. } => Some(method_name.clone()),
+                        _ => None,
+                    });
+                    let mut ft_transfers = vec![];
+                    for (log_index, log) in logs.into_iter().enumerate() {
+                        if log.starts_with(EVENT_LOG_PREFIX) {
+                            let log_index = u16::try_from(log_index).expect("Log index overflow");
+                            let event = parse_event(&log.as_str()[EVENT_LOG_PREFIX.len()..]);
+                            if let Some(mut event) = event {
+                                let data = event.data.take().unwrap_or_default();
+                                for (data_index, data) in data.into_iter().enumerate() {
+                                    let data_index =
+                                        u16::try_from(data_index).expect("Data index overflow");
+                                    let data_json = extract_event_data_json(&data, config);
+                                    let EventExtraFields {
+                                        strings: data_extra_strings,
+                                        amounts: data_extra_amounts,
+                                    } = extract_event_extra_fields(&event, &data, config);
+                                    ft_transfers.extend(extract_event_ft_transfer(
+                                        &event,
+                                        &data,
+                                        method_name.as_deref(),
+                                    ));
+                                    if let Some((kind, old_owner_id, new_owner_id)) =
+                                        extract_event_nft_transfer(&event, &data)
+                                    {
+                                        for (token_index, token_id) in
+                                            data.token_ids.iter().flatten().enumerate()
+                                        {
+                                            nft_transfer_rows.push(NftTransferRow {
+                                                block_height,
+                                                block_hash: block_hash.clone(),
+                                                block_timestamp,
+                                                transaction_hash: transaction_hash.clone(),
+                                                receipt_id: receipt_id.clone(),
+                                                receipt_index,
+                                                log_index,
+                                                data_index,
+                                                token_index: u32::try_from(token_index)
+                                                    .expect("Token index overflow"),
+                                                predecessor_id: predecessor_id.clone(),
+                                                contract_id: account_id.clone(),
+                                                kind,
+                                                token_id: token_id.clone(),
+                                                old_owner_id: old_owner_id.clone(),
+                                                new_owner_id: new_owner_id.clone(),
+                                                authorized_id: data
+                                                    .authorized_id
+                                                    .as_ref()
+                                                    .map(|authorized_id| authorized_id.to_string()),
+                                                memo: data.memo.clone(),
+                                                status,
+                                            });
+                                        }
+                                    }
+                                    event_rows.push(EventRow {
+                                        block_height,
+                                        block_hash: block_hash.clone(),
+                                        block_timestamp,
+                                        transaction_hash: transaction_hash.clone(),
+                                        receipt_id: receipt_id.clone(),
+                                        receipt_index,
+                                        log_index,
+                                        data_index,
+                                        signer_id: signer_id.to_string(),
+                                        signer_public_key: signer_public_key.to_string(),
+                                        predecessor_id: predecessor_id.clone(),
+                                        account_id: account_id.clone(),
+                                        status,
+
+                                        version: event.version.clone(),
+                                        standard: event.standard.clone(),
+                                        event: event.event.clone(),
+                                        data_account_id: data
+                                            .account_id
+                                            .as_ref()
+                                            .map(|account_id| account_id.to_string()),
+                                        data_owner_id: data
+                                            .owner_id
+                                            .as_ref()
+                                            .map(|owner_id| owner_id.to_string()),
+                                        data_old_owner_id: data
+                                            .old_owner_id
+                                            .as_ref()
+                                            .map(|old_owner_id| old_owner_id.to_string()),
+                                        data_new_owner_id: data
+                                            .new_owner_id
+                                            .as_ref()
+                                            .map(|new_owner_id| new_owner_id.to_string()),
+                                        data_liquidation_account_id: data
+                                            .liquidation_account_id
+                                            .as_ref()
+                                            .map(|liquidation_account_id| {
+                                                liquidation_account_id.to_string()
+                                            }),
+                                        data_authorized_id: data
+                                            .authorized_id
+                                            .as_ref()
+                                            .map(|authorized_id| authorized_id.to_string()),
+                                        data_token_ids: limit_token_ids(&data.token_ids),
+                                        data_token_id: data
+                                            .token_id
+                                            .filter(|token_id| token_id.len() <= MAX_TOKEN_LENGTH),
+                                        data_position: data.position,
+                                        data_amount: data
+                                            .amount
+                                            .as_ref()
+                                            .and_then(|amount| amount.parse().ok()),
+                                        data_json,
+                                        data_extra_strings,
+                                        data_extra_amounts,
+                                    });
+                                }
+                            }
+                        }
+                    }
+
+                    let actions = expand_actions(
+                        actions,
+                        signer_id.to_string(),
+                        signer_public_key.to_string(),
+                        &predecessor_id,
+                        &account_id,
+                    );
+                    for ExpandedAction {
+                        action_index,
+                        parent_action_index,
+                        signer_id,
+                        signer_public_key,
+                        predecessor_id,
+                        account_id,
+                        relayer_id,
+                        action,
+                    } in actions
+                    {
+                        let args_data = extract_args_data(&action);
+                        let (args_json, args_is_json) = extract_args_json(&action, config);
+                        if let (
+                            None,
+                            ActionView::FunctionCall { method_name, .. },
+                            Some(args_data),
+                        ) = (parent_action_index, &action, &args_data)
+                        {
+                            ft_transfers.extend(extract_call_ft_transfer(
+                                method_name,
+                                args_data,
+                                &predecessor_id,
+                                &account_id,
+                                return_value_int,
+                            ));
+                        }
+                        // The receipt outcome belongs to the delegate action, the inner actions
+                        // are executed in their own receipts.
+                        let is_inner_action = parent_action_index.is_some();
+                        let row = ActionRow {
+                            block_height,
+                            block_hash: block_hash.clone(),
+                            block_timestamp,
+                            transaction_hash: transaction_hash.clone(),
+                            receipt_id: receipt_id.clone(),
+                            receipt_index,
+                            action_index,
+                            parent_action_index,
+                            signer_id,
+                            signer_public_key,
+                            predecessor_id,
+                            account_id,
+                            relayer_id,
+                            is_refund,
+                            status: (!is_inner_action).then_some(status),
+                            action: match action {
+                                ActionView::CreateAccount => ActionKind::CreateAccount,
+                                ActionView::DeployContract { .. } => ActionKind::DeployContract,
+                                ActionView::FunctionCall { .. } => ActionKind::FunctionCall,
+                                ActionView::Transfer { .. } => ActionKind::Transfer,
+                                ActionView::Stake { .. } => ActionKind::Stake,
+                                ActionView::AddKey { .. } => ActionKind::AddKey,
+                                ActionView::DeleteKey { .. } => ActionKind::DeleteKey,
+                                ActionView::DeleteAccount { .. } => ActionKind::DeleteAccount,
+                                ActionView::Delegate { .. } => ActionKind::Delegate,
+                            },
+                            contract_hash: match &action {
+                                ActionView::DeployContract { code } => {
+                                    Some(CryptoHash::hash_bytes(code).to_string())
+                                }
+                                _ => None,
+                            },
+                            public_key: match &action {
+                                ActionView::AddKey { public_key, .. } => {
+                                    Some(public_key.to_string())
+                                }
+                                ActionView::DeleteKey { public_key, .. } => {
+                                    Some(public_key.to_string())
+                                }
+                                ActionView::Delegate {
+                                    delegate_action, ..
+                                } => Some(delegate_action.public_key.to_string()),
+                                _ => None,
+                            },
+                            access_key_contract_id: match &action {
+                                ActionView::AddKey { access_key, .. } => {
+                                    match &access_key.permission {
+                                        AccessKeyPermissionView::FunctionCall {
+                                            receiver_id,
+                                            ..
+                                        } => Some(receiver_id.to_string()),
+                                        _ => None,
+                                    }
+                                }
+                                _ => None,
+                            },
+                            deposit: match &action {
+                                ActionView::Transfer { deposit, .. } => Some(*deposit),
+                                ActionView::Stake { stake, .. } => Some(*stake),
+                                ActionView::FunctionCall { deposit, .. } => Some(*deposit),
+                                _ => None,
+                            },
+                            gas_price,
+                            attached_gas: match &action {
+                                ActionView::FunctionCall { gas, .. } => Some(*gas),
+                                _ => None,
+                            },
+                            gas_burnt: (!is_inner_action).then_some(gas_burnt),
+                            tokens_burnt: (!is_inner_action).then_some(tokens_burnt),
+                            method_name: match &action {
+                                ActionView::FunctionCall { method_name, .. } => {
+                                    Some(method_name.to_string())
+                                }
+                                _ => None,
+                            },
+                            args_account_id: args_data.as_ref().and_then(|args| {
+                                args.account_id
+                                    .as_ref()
+                                    .map(|account_id| account_id.to_string())
+                            }),
+                            args_new_account_id: args_data.as_ref().and_then(|args| {
+                                args.args_new_account_id
+                                    .as_ref()
+                                    .map(|new_account_id| new_account_id.to_string())
+                            }),
+                            args_owner_id: args_data.as_ref().and_then(|args| {
+                                args.args_owner_id
+                                    .as_ref()
+                                    .map(|owner_id| owner_id.to_string())
+                            }),
+                            args_receiver_id: args_data.as_ref().and_then(|args| {
+                                args.receiver_id
+                                    .as_ref()
+                                    .map(|receiver_id| receiver_id.to_string())
+                            }),
+                            args_sender_id: args_data.as_ref().and_then(|args| {
+                                args.sender_id
+                                    .as_ref()
+                                    .map(|sender_id| sender_id.to_string())
+                            }),
+                            args_token_id: args_data
+                                .as_ref()
+                                .and_then(|args| args.token_id.clone()),
+                            args_amount: args_data.as_ref().and_then(|args| {
+                                args.amount.as_ref().and_then(|amount| amount.parse().ok())
+                            }),
+                            args_balance: args_data.as_ref().and_then(|args| {
+                                args.balance
+                                    .as_ref()
+                                    .and_then(|balance| balance.parse().ok())
+                            }),
+                            args_nft_contract_id: args_data.as_ref().and_then(|args| {
+                                args.nft_contract_id
+                                    .as_ref()
+                                    .map(|nft_contract_id| nft_contract_id.to_string())
+                            }),
+                            args_nft_token_id: args_data.as_ref().and_then(|args| {
+                                args.nft_token_id
+                                    .as_ref()
+                                    .map(|nft_token_id| nft_token_id.to_string())
+                            }),
+                            args_utm_source: args_data.as_ref().and_then(|args| {
+                                args.utm_source
+                                    .as_ref()
+                                    .map(|utm_source| utm_source.to_string())
+                            }),
+                            args_utm_medium: args_data.as_ref().and_then(|args| {
+                                args.utm_medium
+                                    .as_ref()
+                                    .map(|utm_medium| utm_medium.to_string())
+                            }),
+                            args_utm_campaign: args_data.as_ref().and_then(|args| {
+                                args.utm_campaign
+                                    .as_ref()
+                                    .map(|utm_campaign| utm_campaign.to_string())
+                            }),
+                            args_utm_term: args_data.as_ref().and_then(|args| {
+                                args.utm_term.as_ref().map(|utm_term| utm_term.to_string())
+                            }),
+                            args_utm_content: args_data.as_ref().and_then(|args| {
+                                args.utm_content
+                                    .as_ref()
+                                    .map(|utm_content| utm_content.to_string())
+                            }),
+                            args_json,
+                            args_is_json,
+                            return_value_int: return_value_int.filter(|_| !is_inner_action),
+                            return_value_json: return_value_json
+                                .clone()
+                                .filter(|_| !is_inner_action),
+                            return_value_kind: return_value_kind.filter(|_| !is_inner_action),
+                        };
+                        action_rows.push(row);
+                    }
+
+                    // Contracts with NEP-141 events don't need the function call heuristics.
+                    if ft_transfers
+                        .iter()
+                        .any(|transfer| transfer.source == FtTransferSource::Event)
+                    {
+                        ft_transfers.retain(|transfer| transfer.source == FtTransferSource::Event);
+                    }
+                    for (transfer_index, transfer) in ft_transfers.into_iter().enumerate() {
+                        ft_transfer_rows.push(FtTransferRow {
+                            block_height,
+                            block_hash: block_hash.clone(),
+                            block_timestamp,
+                            transaction_hash: transaction_hash.clone(),
+                            receipt_id: receipt_id.clone(),
+                            receipt_index,
+                            transfer_index: u16::try_from(transfer_index)
+                                .expect("Transfer index overflow"),
+                            predecessor_id: predecessor_id.clone(),
+                            token_id: account_id.clone(),
+                            kind: transfer.kind,
+                            source: transfer.source,
+                            from_id: transfer.from_id,
+                            to_id: transfer.to_id,
+                            amount: transfer.amount,
+                            memo: transfer.memo,
+                            status,
+                        });
+                    }
+                }
+                ReceiptEnumView::Data { .. } => {}
+            }
+            receipt_index = receipt_index
+                .checked_add(1)
+                .expect("Receipt index overflow");
+        }
+    }
+    BlockRows {
+        actions: action_rows,
+        events: event_rows,
+        transactions: transaction_rows,
+        account_changes: account_change_rows,
+        access_key_changes: access_key_change_rows,
+        receipts: receipt_rows,
+        data_receipts: data_receipt_rows,
+        blocks: block_rows,
+        chunks: chunk_rows,
+        contract_deployments: contract_deployment_rows,
+        ft_transfers: ft_transfer_rows,
+        nft_transfers: nft_transfer_rows,
+        contract_codes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract::config::EventFieldsConfig;
+    use fastnear_primitives::near_indexer_primitives::StreamerMessage;
+    use fastnear_primitives::near_primitives::errors::{
+        ActionError, ActionErrorKind, InvalidTxError,
+    };
+    use serde_json::json;
+
+    const FIXTURE: &str = include_str!("../../../tests/fixtures/streamer_message.json");
+
+    /// Returns the fixture block after applying the edit to its JSON.
+    fn fixture_block(edit: impl FnOnce(&mut Value)) -> BlockWithTxHashes {
+        let mut json: Value = serde_json::from_str(FIXTURE).unwrap();
+        edit(&mut json);
+        serde_json::from_value::<StreamerMessage>(json)
+            .unwrap()
+            .into()
+    }
+
+    /// The first receipt of the fixture: an `ft_transfer` call on `token.near` by `alice.near`
+    /// that returns `"1000"`.
+    fn first_receipt(json: &mut Value) -> &mut Value {
+        &mut json["shards"][0]["receipt_execution_outcomes"][0]
+    }
+
+    #[test]
+    fn test_inner_actions_have_no_receipt_outcome() {
+        let block = fixture_block(|json| {
+            first_receipt(json)["receipt"]["receipt"]["Action"]["actions"] = json!([{
+                "Delegate": {
+                    "delegate_action": {
+                        "sender_id": "carol.near",
+                        "receiver_id": "token.near",
+                        "actions": [
+                            {"FunctionCall": {"method_name": "ft_transfer", "args": "e30=", "gas": 1000, "deposit": "1"}},
+                            {"Transfer": {"deposit": "5"}}
+                        ],
+                        "nonce": 1,
+                        "max_block_height": 120000100,
+                        "public_key": "ed25519:ETHdYC8gNjfa7QzaAZzEJPU1aAFPZUKacGCNKrpvTpTB"
+                    },
+                    "signature": "ed25519:4ZUHL63NfqFgXtDpuE9MXJvM9neS6qQffoEDj5q6Xx9r3XCy7dNW7g8jZmysSAPVukvaT1LNWZ2Qiihm8F2BsCws"
+                }
+            }]);
+        });
+        let receipt_id = "9pBigwAoNur7yAr4Rvy4WRg3fp1gm1nNhv2QVRXsBGWB";
+        let actions = extract_rows(block, &ExtractConfig::default())
+            .actions
+            .into_iter()
+            .filter(|action| action.receipt_id == receipt_id)
+            .collect::<Vec<_>>();
+        assert_eq!(actions.len(), 3);
+
+        let delegate = &actions[0];
+        assert_eq!(delegate.action, ActionKind::Delegate);
+        assert_eq!(delegate.parent_action_index, None);
+        assert_eq!(delegate.status, Some(ReceiptStatus::Success));
+        assert_eq!(delegate.gas_burnt, Some(2428000000000));
+        assert_eq!(delegate.tokens_burnt, Some(242800000000000000000));
+        assert_eq!(delegate.return_value_int, Some(1000));
+        assert_eq!(delegate.return_value_kind, Some(ReturnValueKind::Json));
+
+        for (action_index, (inner, kind)) in actions[1..]
+            .iter()
+            .zip([ActionKind::FunctionCall, ActionKind::Transfer])
+            .enumerate()
+        {
+            assert_eq!(inner.action, kind);
+            assert_eq!(inner.action_index, action_index as u8);
+            assert_eq!(inner.parent_action_index, Some(0));
+            assert_eq!(inner.predecessor_id, "carol.near");
+            assert_eq!(inner.relayer_id.as_deref(), Some("alice.near"));
+            assert_eq!(inner.status, None);
+            assert_eq!(inner.gas_burnt, None);
+            assert_eq!(inner.tokens_burnt, None);
+            assert_eq!(inner.return_value_int, None);
+            assert_eq!(inner.return_value_json, None);
+            assert_eq!(inner.return_value_kind, None);
+        }
+    }
+
+    #[test]
+    fn test_extract_failure_of_action_error() {
+        let status = ExecutionStatusView::Failure(TxExecutionError::ActionError(ActionError {
+            index: Some(1),
+            kind: ActionErrorKind::AccountDoesNotExist {
+                account_id: "bob.near".parse().unwrap(),
+            },
+        }));
+        let (failure_kind, failure_action_index, failure_json) = extract_failure(&status);
+        assert_eq!(failure_kind.as_deref(), Some("AccountDoesNotExist"));
+        assert_eq!(failure_action_index, Some(1));
+        assert_eq!(
+            serde_json::from_str::<Value>(&failure_json.unwrap()).unwrap(),
+            json!({"ActionError": {"index": 1, "kind": {"AccountDoesNotExist": {"account_id": "bob.near"}}}})
+        );
+    }
+
+    #[test]
+    fn test_extract_failure_of_invalid_tx_error() {
+        // A unit variant is serialized as a string.
+        let status = ExecutionStatusView::Failure(TxExecutionError::InvalidTxError(
+            InvalidTxError::InvalidSignature,
+        ));
+        let (failure_kind, failure_action_index, failure_json) = extract_failure(&status);
+        assert_eq!(failure_kind.as_deref(), Some("InvalidSignature"));
+        assert_eq!(failure_action_index, None);
+        assert_eq!(
+            failure_json.as_deref(),
+            Some(r#"{"InvalidTxError":"InvalidSignature"}"#)
+        );
+
+        let (failure_kind, _, _) = extract_failure(&ExecutionStatusView::Failure(
+            TxExecutionError::InvalidTxError(InvalidTxError::InvalidNonce {
+                tx_nonce: 1,
+                ak_nonce: 2,
+            }),
+        ));
+        assert_eq!(failure_kind.as_deref(), Some("InvalidNonce"));
+    }
+
+    #[test]
+    fn test_extract_failure_of_success() {
+        assert_eq!(
+            extract_failure(&ExecutionStatusView::SuccessValue(vec![])),
+            (None, None, None)
+        );
+    }
+
+    #[test]
+    fn test_enum_variant_name() {
+        assert_eq!(
+            enum_variant_name(&InvalidTxError::Expired).as_deref(),
+            Some("Expired")
+        );
+        assert_eq!(enum_variant_name(&1u8), None);
+    }
+
+    #[test]
+    fn test_parse_event_keeps_data_with_mismatched_fields() {
+        let event = parse_event(
+            r#"{"standard":"nep171","version":"1.0.0","event":"nft_mint","data":[{"owner_id":"alice.near","token_ids":["1"]},{"owner_id":"Not An Account","token_ids":["2"],"extra":7}]}"#,
+        )
+        .unwrap();
+        let data = event.data.unwrap();
+        assert_eq!(data.len(), 2);
+        assert_eq!(data[0].owner_id.as_ref().unwrap().as_str(), "alice.near");
+        assert_eq!(data[0].token_ids, Some(vec!["1".to_string()]));
+        // The invalid account ID fails the typed parse, so all known fields are empty.
+        assert!(data[1].owner_id.is_none());
+        assert!(data[1].token_ids.is_none());
+        assert_eq!(data[1].raw["extra"], 7);
+    }
+
+    #[test]
+    fn test_parse_event_accepts_single_data_object() {
+        let event = parse_event(r#"{"standard":"nep141","event":"ft_mint","data":{"amount":"5"}}"#)
+            .unwrap();
+        let data = event.data.unwrap();
+        assert_eq!(data.len(), 1);
+        assert_eq!(data[0].amount.as_deref(), Some("5"));
+    }
+
+    #[test]
+    fn test_parse_event_drops_invalid_json() {
+        assert!(parse_event("not json").is_none());
+        assert!(parse_event(r#"["nep141"]"#).is_none());
+    }
+
+    #[test]
+    fn test_extract_event_extra_fields() {
+        let event_fields: EventFieldsConfig = serde_json::from_str(
+            r#"{"nep245":{"mt_transfer":{"new_owner_id":"string","token_ids":"string"},"*":{"amount":"amount","fee":"amount"}}}"#,
+        )
+        .unwrap();
+        let config = ExtractConfig {
+            event_fields,
+            ..Default::default()
+        };
+        let mut event = parse_event(
+            r#"{"standard":"nep245","event":"mt_transfer","data":[{"new_owner_id":"bob.near","token_ids":["a","b"],"amount":"100","fee":3}]}"#,
+        )
+        .unwrap();
+        let data = event.data.take().unwrap().pop().unwrap();
+        assert_eq!(
+            extract_event_extra_fields(&event, &data, &config),
+            EventExtraFields {
+                strings: vec![
+                    ("new_owner_id".to_string(), "bob.near".to_string()),
+                    ("token_ids".to_string(), r#"["a","b"]"#.to_string()),
+                ],
+                amounts: vec![("amount".to_string(), 100), ("fee".to_string(), 3)],
+            }
+        );
+        event.event = Some("mt_burn".to_string());
+        assert_eq!(
+            extract_event_extra_fields(&event, &data, &config).strings,
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_extract_data_receipts() {
+        let block = fixture_block(|_| {});
+        let data_receipts = extract_rows(block, &ExtractConfig::default()).data_receipts;
+        assert_eq!(data_receipts.len(), 2);
+
+        let resolved = &data_receipts[0];
+        assert_eq!(
+            resolved.receipt_id,
+            "FmiQSKCzinb4teE8VzGNqXXph5tcnjmyttEDD78Bfpyh"
+        );
+        assert_eq!(resolved.data_receipt_index, 0);
+        assert_eq!(
+            resolved.data_id,
+            "ANsRM7dnBLTX3ER8DfGjYqvY3RVa2YaQXSKgE2NLrECk"
+        );
+        assert_eq!(resolved.predecessor_id, "token.near");
+        assert_eq!(resolved.account_id, "alice.near");
+        // The data is `"1000"` with the quotes.
+        assert_eq!(resolved.data_length, Some(6));
+        assert!(!resolved.is_promise_resume);
+
+        let failed = &data_receipts[1];
+        assert_eq!(failed.data_receipt_index, 1);
+        assert_eq!(failed.data_length, None);
+        assert!(failed.is_promise_resume);
+    }
+
+    #[test]
+    fn test_is_refund() {
+        let block = fixture_block(|_| {});
+        let actions = extract_rows(block, &ExtractConfig::default()).actions;
+        let refund = actions
+            .iter()
+            .find(|action| action.receipt_id == "BxzFHg4UW3BdSTAvH5b3MyJEBQ2W6kztUAqdsEo71iru")
+            .unwrap();
+        assert_eq!(refund.predecessor_id, "system");
+        assert!(refund.is_refund);
+        assert!(actions
+            .iter()
+            .filter(|action| action.predecessor_id != "system")
+            .all(|action| !action.is_refund));
+    }
+
+    /// Replaces the function call args of the first receipt with the given JSON.
+    fn set_first_receipt_args(json: &mut Value, args: Value) {
+        first_receipt(json)["receipt"]["receipt"]["Action"]["actions"][0]["FunctionCall"]["args"] =
+            json!(base64::engine::general_purpose::STANDARD.encode(args.to_string()));
+    }
+
+    #[test]
+    fn test_extract_ft_transfers_from_events() {
+        let block = fixture_block(|json| {
+            set_first_receipt_args(json, json!({"receiver_id": "carol.near", "amount": "5"}));
+        });
+        let ft_transfers = extract_rows(block, &ExtractConfig::default()).ft_transfers;
+        // The legacy call is ignored, because the receipt has NEP-141 events.
+        assert_eq!(ft_transfers.len(), 1);
+        let transfer = &ft_transfers[0];
+        assert_eq!(
+            transfer.receipt_id,
+            "9pBigwAoNur7yAr4Rvy4WRg3fp1gm1nNhv2QVRXsBGWB"
+        );
+        assert_eq!(transfer.token_id, "token.near");
+        assert_eq!(transfer.kind, FtTransferKind::Transfer);
+        assert_eq!(transfer.source, FtTransferSource::Event);
+        assert_eq!(transfer.from_id.as_deref(), Some("alice.near"));
+        assert_eq!(transfer.to_id.as_deref(), Some("bob.near"));
+        assert_eq!(transfer.amount, 1000);
+        assert_eq!(transfer.status, ReceiptStatus::Success);
+    }
+
+    #[test]
+    fn test_extract_ft_transfers_from_legacy_calls() {
+        let block = fixture_block(|json| {
+            first_receipt(json)["execution_outcome"]["outcome"]["logs"] = json!([]);
+            set_first_receipt_args(
+                json,
+                json!({"receiver_id": "carol.near", "amount": "5", "memo": "hi"}),
+            );
+        });
+        let ft_transfers = extract_rows(block, &ExtractConfig::default()).ft_transfers;
+        assert_eq!(ft_transfers.len(), 1);
+        let transfer = &ft_transfers[0];
+        assert_eq!(transfer.kind, FtTransferKind::Transfer);
+        assert_eq!(transfer.source, FtTransferSource::FunctionCall);
+        assert_eq!(transfer.from_id.as_deref(), Some("alice.near"));
+        assert_eq!(transfer.to_id.as_deref(), Some("carol.near"));
+        assert_eq!(transfer.amount, 5);
+        assert_eq!(transfer.memo.as_deref(), Some("hi"));
+    }
+
+    #[test]
+    fn test_extract_call_ft_transfer_refund() {
+        let args_data: ArgsData = serde_json::from_value(json!({
+            "sender_id": "alice.near",
+            "receiver_id": "bob.near",
+            "amount": "1000",
+        }))
+        .unwrap();
+        let refund = |predecessor_id: &str, return_value_int: Option<u128>| {
+            extract_call_ft_transfer(
+                "ft_resolve_transfer",
+                &args_data,
+                predecessor_id,
+                "token.near",
+                return_value_int,
+            )
+        };
+        // The return value is the used amount, so the rest goes back to the sender.
+        let transfer = refund("token.near", Some(600)).unwrap();
+        assert_eq!(transfer.kind, FtTransferKind::Refund);
+        assert_eq!(transfer.source, FtTransferSource::FunctionCall);
+        assert_eq!(transfer.from_id.as_deref(), Some("bob.near"));
+        assert_eq!(transfer.to_id.as_deref(), Some("alice.near"));
+        assert_eq!(transfer.amount, 400);
+        // Nothing to refund if the full amount was used.
+        assert!(refund("token.near", Some(1000)).is_none());
+        // The used amount can't be larger than the transferred amount.
+        assert!(refund("token.near", Some(1001)).is_none());
+        assert!(refund("token.near", None).is_none());
+        // Only the token contract can resolve its transfers.
+        assert!(refund("alice.near", Some(600)).is_none());
+    }
+
+    #[test]
+    fn test_ft_transfer_memo_is_capped() {
+        let memo = |length: usize| "m".repeat(length);
+        let args_data = |memo: String| {
+            extract_args_data(&ActionView::FunctionCall {
+                method_name: "ft_transfer".to_string(),
+                args: json!({"receiver_id": "bob.near", "amount": "1", "memo": memo})
+                    .to_string()
+                    .into_bytes()
+                    .into(),
+                gas: 0,
+                deposit: 1,
+            })
+            .unwrap()
+        };
+        let transfer = |memo: String| {
+            extract_call_ft_transfer(
+                "ft_transfer",
+                &args_data(memo),
+                "alice.near",
+                "token.near",
+                None,
+            )
+            .unwrap()
+        };
+        assert_eq!(
+            transfer(memo(MAX_MEMO_LENGTH)).memo,
+            Some(memo(MAX_MEMO_LENGTH))
+        );
+        // A longer memo is dropped, but the transfer is kept.
+        let long_memo_transfer = transfer(memo(MAX_MEMO_LENGTH + 1));
+        assert_eq!(long_memo_transfer.memo, None);
+        assert_eq!(long_memo_transfer.amount, 1);
+
+        let event_transfer = |memo: String| {
+            let event = parse_event(
+                &json!({
+                    "standard": "nep141",
+                    "event": "ft_transfer",
+                    "data": [{"old_owner_id": "alice.near", "new_owner_id": "bob.near", "amount": "1", "memo": memo}],
+                })
+                .to_string(),
+            )
+            .unwrap();
+            let data = &event.data.as_ref().unwrap()[0];
+            extract_event_ft_transfer(&event, data, Some("ft_transfer")).unwrap()
+        };
+        assert_eq!(
+            event_transfer(memo(MAX_MEMO_LENGTH)).memo,
+            Some(memo(MAX_MEMO_LENGTH))
+        );
+        assert_eq!(event_transfer(memo(MAX_MEMO_LENGTH + 1)).memo, None);
+    }
+
+    #[test]
+    fn test_nft_transfer_keeps_full_memo_and_token_ids() {
+        let memo = "m".repeat(MAX_MEMO_LENGTH + 1);
+        let long_token_id = "t".repeat(MAX_TOKEN_LENGTH + 1);
+        let log = json!({
+            "standard": "nep171",
+            "version": "1.0.0",
+            "event": "nft_transfer",
+            "data": [{
+                "old_owner_id": "alice.near",
+                "new_owner_id": "bob.near",
+                "token_ids": ["1", "2", "3", "4", long_token_id],
+                "memo": memo,
+            }],
+        });
+        let block = fixture_block(|json| {
+            first_receipt(json)["execution_outcome"]["outcome"]["logs"] =
+                json!([format!("{}{}", EVENT_LOG_PREFIX, log)]);
+        });
+        let nft_transfers = extract_rows(block, &ExtractConfig::default()).nft_transfers;
+        assert_eq!(
+            nft_transfers
+                .iter()
+                .map(|transfer| transfer.token_id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["1", "2", "3", "4", long_token_id.as_str()]
+        );
+        for (token_index, transfer) in nft_transfers.iter().enumerate() {
+            assert_eq!(transfer.token_index, token_index as u32);
+            assert_eq!(transfer.kind, NftTransferKind::Transfer);
+            assert_eq!(transfer.contract_id, "token.near");
+            assert_eq!(transfer.old_owner_id.as_deref(), Some("alice.near"));
+            assert_eq!(transfer.new_owner_id.as_deref(), Some("bob.near"));
+            assert_eq!(transfer.memo.as_deref(), Some(memo.as_str()));
+        }
+    }
+
+    fn function_call(args: &[u8]) -> ActionView {
+        ActionView::FunctionCall {
+            method_name: "call".to_string(),
+            args: args.to_vec().into(),
+            gas: 0,
+            deposit: 0,
+        }
+    }
+
+    #[test]
+    fn test_extract_args_json() {
+        let config = ExtractConfig {
+            args_json_max_length: Some(16),
+            ..Default::default()
+        };
+        assert_eq!(
+            extract_args_json(&function_call(br#"{"a":1}"#), &config),
+            (Some(r#"{"a":1}"#.to_string()), Some(true))
+        );
+        // Non-JSON args are stored as a JSON string with base64.
+        assert_eq!(
+            extract_args_json(&function_call(&[1, 2, 255]), &config),
+            (Some(r#""AQL/""#.to_string()), Some(false))
+        );
+        // Too long args are not stored, but the flag is.
+        assert_eq!(
+            extract_args_json(&function_call(br#"{"a":"0123456789"}"#), &config),
+            (None, Some(true))
+        );
+        assert_eq!(
+            extract_args_json(&ActionView::Transfer { deposit: 1 }, &config),
+            (None, None)
+        );
+        // The args are not parsed without the max length.
+        assert_eq!(
+            extract_args_json(&function_call(br#"{"a":1}"#), &ExtractConfig::default()),
+            (None, None)
+        );
+    }
+
+    #[test]
+    fn test_bytes_to_json_of_invalid_utf8() {
+        assert_eq!(bytes_to_json(&[b'"', 255, b'"'], true, 16), None);
+        assert_eq!(
+            bytes_to_json(&[b'"', 255, b'"'], false, 16),
+            Some(r#""Iv8i""#.to_string())
+        );
+    }
+
+    #[test]
+    fn test_extract_return_value_json() {
+        let config = ExtractConfig {
+            return_value_json_max_length: Some(8),
+            ..Default::default()
+        };
+        let success = |value: &[u8]| ExecutionStatusView::SuccessValue(value.to_vec());
+        assert_eq!(
+            extract_return_value_json(&success(b"true"), &config),
+            (Some("true".to_string()), Some(ReturnValueKind::Json))
+        );
+        assert_eq!(
+            extract_return_value_json(&success(&[1, 2, 255]), &config),
+            (Some(r#""AQL/""#.to_string()), Some(ReturnValueKind::Binary))
+        );
+        // Too long values are not stored, but the kind is.
+        assert_eq!(
+            extract_return_value_json(&success(br#""0123456789""#), &config),
+            (None, Some(ReturnValueKind::Json))
+        );
+        assert_eq!(
+            extract_return_value_json(&success(b""), &config),
+            (None, Some(ReturnValueKind::Empty))
+        );
+        assert_eq!(
+            extract_return_value_json(
+                &ExecutionStatusView::SuccessReceiptId(CryptoHash::default()),
+                &config
+            ),
+            (None, Some(ReturnValueKind::ReceiptId))
+        );
+        assert_eq!(
+            extract_return_value_json(&ExecutionStatusView::Unknown, &config),
+            (None, None)
+        );
+        // The kind is stored without the max length.
+        assert_eq!(
+            extract_return_value_json(&success(b"true"), &ExtractConfig::default()),
+            (None, Some(ReturnValueKind::Json))
+        );
+    }
+}
@@ -1,10 +1,18 @@
-mod click;
+mod block_source;
 mod common;
+// Only the actions and events are used here. The other rows and the table schemas are only used
+// by the ClickHouse indexers.
+#[allow(dead_code)]
+mod extract;
+mod lake_reader;
 mod redis_db;
+mod xblock;
 
-use crate::click::{extract_rows, ActionKind, ActionRow, EventRow, ReceiptStatus};
+use crate::block_source::BlockSource;
+use crate::extract::{
+    extract_rows, ActionKind, ActionRow, BlockRows, EventRow, ExtractConfig, ReceiptStatus,
+};
 use dotenv::dotenv;
-use fastnear_neardata_fetcher::fetcher;
 use fastnear_primitives::block_with_tx_hash::BlockWithTxHashes;
 use fastnear_primitives::near_primitives::account::id::AccountType;
 use fastnear_primitives::near_primitives::types::{AccountId, BlockHeight};
@@ -21,10 +29,6 @@ use tokio::sync::mpsc;
 
 const PROJECT_ID: &str = "ft_red";
 
-const FINAL_BLOCKS_KEY: &str = "final_blocks";
-const BLOCK_KEY: &str = "block";
-const SAFE_OFFSET: u64 = 100;
-
 #[derive(Debug, Eq, PartialEq, Hash, Clone)]
 pub struct PairUpdate {
     account_id: String,
@@ -58,7 +62,9 @@ async fn main() {
     })
     .expect("Error setting Ctrl+C handler");
 
-    common::setup_tracing("ft_red=info,redis=info,clickhouse=info,neardata-fetcher=info");
+    common::setup_tracing(
+        "ft_red=info,redis=info,clickhouse=info,neardata-fetcher=info,block_source=info",
+    );
 
     tracing::log::info!(target: PROJECT_ID, "Starting FT Redis Indexer");
 
@@ -67,16 +73,9 @@ async fn main() {
     ))
     .await;
 
-    let client = reqwest::Client::new();
     let chain_id = ChainId::try_from(std::env::var("CHAIN_ID").expect("CHAIN_ID is not set"))
         .expect("Invalid chain id");
-    let first_block_height = fetcher::fetch_first_block(&client, chain_id)
-        .await
-        .expect("First block doesn't exists")
-        .block
-        .header
-        .height;
-    tracing::log::info!(target: PROJECT_ID, "First redis block {}", first_block_height);
+    let block_source = BlockSource::from_env("neardata");
 
     let last_block_height: Option<BlockHeight> = write_redis_db
         .get("meta:latest_block")
@@ -91,36 +90,28 @@ async fn main() {
     });
     tracing::log::info!(target: PROJECT_ID, "Resuming from {}", start_block_height);
 
-    let num_threads = std::env::var("NUM_THREADS")
-        .map(|s| s.parse::<u64>().expect("Failed to parse NUM_THREADS"))
-        .unwrap_or(4);
-
-    let (sender, receiver) = mpsc::channel(100);
-    let config = fetcher::FetcherConfig {
-        num_threads,
-        start_block_height,
-        chain_id,
-    };
-    tokio::spawn(fetcher::start_fetcher(
-        Some(client),
-        config,
-        sender,
-        is_running,
-    ));
-
-    listen_blocks(receiver, write_redis_db, chain_id).await;
+    let stream = block_source.streamer(start_block_height, is_running);
+
+    let extract_config = ExtractConfig::from_env();
+
+    listen_blocks(stream, write_redis_db, chain_id, extract_config).await;
 }
 
 async fn listen_blocks(
     mut stream: mpsc::Receiver<BlockWithTxHashes>,
     mut redis_db: RedisDB,
     chain_id: ChainId,
+    extract_config: ExtractConfig,
 ) {
     while let Some(streamer_message) = stream.recv().await {
         let block_height = streamer_message.block.header.height;
         let block_timestamp = streamer_message.block.header.timestamp_nanosec;
         tracing::log::info!(target: PROJECT_ID, "Processing block: {}", block_height);
-        let (actions, events) = extract_rows(streamer_message);
+        // The inner actions of delegate actions don't have a status, so they are skipped like the
+        // failed ones. They are executed in their own receipts later.
+        let BlockRows {
+            actions, events, ..
+        } = extract_rows(streamer_message, &extract_config);
 
         let mut to_update: HashMap<String, Vec<(String, String)>> = HashMap::new();
 
@@ -205,7 +196,9 @@ fn extract_staking_pairs(actions: &[ActionRow], _chain_id: ChainId) -> HashSet<P
     // Extract matching (account_id, validator_id) for staking changes
     let mut pairs = HashSet::new();
     for action in actions {
-        if action.status != ReceiptStatus::Success || action.action != ActionKind::FunctionCall {
+        if action.status != Some(ReceiptStatus::Success)
+            || action.action != ActionKind::FunctionCall
+        {
             continue;
         }
         if action.account_id.ends_with(".poolv1.near")
@@ -229,13 +222,13 @@ fn extract_public_keys(
     // Extract matching (account_id, validator_id) for staking changes
     let mut pairs = HashMap::new();
     for action in actions {
-        if action.status != ReceiptStatus::Success {
+        if action.status != Some(ReceiptStatus::Success) {
             continue;
         }
         match action.action {
             ActionKind::AddKey | ActionKind::DeleteKey => {
                 let public_key = PublicKey::from_str(
-                    &action
+                    action
                         .public_key
                         .as_ref()
                         .expect("Missing PublicKey for AddKey action"),
@@ -270,7 +263,7 @@ fn extract_public_keys(
                 let account_id =
                     AccountId::from_str(&action.account_id).expect("Invalid account_id");
                 if account_id.get_account_type() == AccountType::NearImplicitAccount {
-                    let bytes = hex::decode(&account_id.as_str()).expect("Invalid hex");
+                    let bytes = hex::decode(account_id.as_str()).expect("Invalid hex");
                     let public_key = PublicKey::ED25519(bytes.as_slice().try_into().unwrap());
                     pairs.insert(
                         PublicKeyPair {
@@ -296,7 +289,7 @@ fn extract_ft_pairs(
     // Extract matching (account_id, token_id) for FT changes
     let mut pairs = HashSet::new();
     for action in actions {
-        if action.status != ReceiptStatus::Success {
+        if action.status != Some(ReceiptStatus::Success) {
             continue;
         }
         let token_id = &action.account_id;
@@ -312,13 +305,13 @@ fn extract_ft_pairs(
         }
         let method_name = action.method_name.as_ref().unwrap();
         // Special case for HERE staking contract
-        if token_id == "storage.herewallet.near" {
-            if ["deposit", "withdraw"].contains(&method_name.as_str()) {
-                pairs.insert(PairUpdate {
-                    account_id: action.predecessor_id.clone(),
-                    token_id: token_id.clone(),
-                });
-            }
+        if token_id == "storage.herewallet.near"
+            && ["deposit", "withdraw"].contains(&method_name.as_str())
+        {
+            pairs.insert(PairUpdate {
+                account_id: action.predecessor_id.clone(),
+                token_id: token_id.clone(),
+            });
         }
         if token_id.ends_with(".factory.bridge.near")
             || token_id == "aurora"
@@ -404,7 +397,7 @@ fn extract_nft_pairs(
     // Extract matching (account_id, token_id) for FT changes
     let mut pairs = HashSet::new();
     for action in actions {
-        if action.status != ReceiptStatus::Success {
+        if action.status != Some(ReceiptStatus::Success) {
             continue;
         }
         let token_id = &action.account_id;
@@ -479,7 +472,7 @@ fn add_pairs_to_update(
     {
         to_update
             .entry(format!("{}:{}", prefix, account_id))
-            .or_insert_with(Vec::new)
+            .or_default()
             .push((token_id, block_height.to_string()));
     }
 }
@@ -488,7 +481,7 @@ fn extract_all_accounts(actions: &[ActionRow], _chain_id: ChainId) -> HashSet<St
     actions
         .iter()
         .filter_map(|action| {
-            if action.status == ReceiptStatus::Success {
+            if action.status == Some(ReceiptStatus::Success) {
                 Some(action.account_id.clone())
             } else {
                 None
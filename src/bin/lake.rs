@@ -1,143 +1,248 @@
+mod block_source;
 mod click;
 mod common;
+mod extract;
+mod lake_reader;
+mod redis_db;
+mod xblock;
 
+use block_source::{ArchiveConfig, BlockSource, LakeConfig};
 use click::*;
 use dotenv::dotenv;
-use flate2::read::GzDecoder;
-use near_indexer::near_primitives::types::BlockHeight;
-use near_indexer::StreamerMessage;
-use std::collections::HashMap;
-use std::io::Read;
-use std::{env, fs};
+use fastnear_primitives::block_with_tx_hash::BlockWithTxHashes;
+use fastnear_primitives::near_primitives::types::BlockHeight;
+use std::collections::VecDeque;
+use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use tokio::sync::mpsc;
 
 const PROJECT_ID: &str = "lake_indexer";
-const BLOCK_FOLDER: &str = "block";
+const MIN_BATCH: usize = 20_000;
+const DEFAULT_RANGE_SIZE: BlockHeight = 100_000;
 
-pub struct Config {
-    pub from_block: BlockHeight,
-    pub to_block: BlockHeight,
-    pub path: String,
-}
+#[tokio::main]
+async fn main() {
+    openssl_probe::init_ssl_cert_env_vars();
+    dotenv().ok();
 
-fn read_folder(path: &str) -> Vec<String> {
-    let mut entries: Vec<String> = fs::read_dir(path)
-        .unwrap()
-        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
-        .collect();
-    entries.sort();
-    entries
-}
+    common::setup_tracing(
+        "clickhouse=info,lake_indexer=info,block_source=info,lake_reader=info,metrics=info",
+    );
+
+    tracing::log::info!(target: PROJECT_ID, "Starting NEAR Lake Indexer");
+
+    let is_running = Arc::new(AtomicBool::new(true));
+    let ctrl_c_running = is_running.clone();
+    ctrlc::set_handler(move || {
+        ctrl_c_running.store(false, Ordering::SeqCst);
+        println!("Received Ctrl+C, starting shutdown...");
+    })
+    .expect("Error setting Ctrl+C handler");
 
-fn read_archive(path: &str) -> HashMap<String, String> {
-    if !std::path::Path::new(path).exists() {
-        return HashMap::new();
+    let mut db = ClickDB::new(PROJECT_ID, PROJECT_ID, MIN_BATCH);
+
+    if migrate_if_requested(&db).await {
+        return;
+    }
+    db.validate_schema()
+        .await
+        .expect("Invalid ClickHouse schema");
+    start_metrics_server();
+
+    let from_block: BlockHeight = env::var("FROM_BLOCK")
+        .expect("FROM_BLOCK is required")
+        .parse()
+        .unwrap();
+    let block_source = BlockSource::from_env("lake");
+    let to_block = match &block_source {
+        BlockSource::Lake(config) => config.to_block,
+        BlockSource::Archive(config) => config.to_block,
+        _ => panic!("The lake indexer needs a BLOCK_SOURCE with TO_BLOCK: `lake` or `archive`"),
+    };
+
+    let num_workers: usize = env::var("NUM_WORKERS")
+        .map(|s| s.parse().expect("Invalid NUM_WORKERS"))
+        .unwrap_or(1);
+    if num_workers > 1 {
+        backfill_in_parallel(from_block, to_block, block_source, num_workers, is_running).await;
+        return;
     }
-    tar::Archive::new(GzDecoder::new(std::fs::File::open(path).unwrap()))
-        .entries()
-        .unwrap()
-        .filter_map(|e| e.ok())
-        .map(|mut e| {
-            let path = e.path().unwrap().to_string_lossy().to_string();
-            let mut content = String::new();
-            e.read_to_string(&mut content).unwrap();
-            (path, content)
+
+    let last_block_height = db
+        .load_checkpoint()
+        .await
+        .expect("Failed to get the checkpoint from clickhouse");
+    // Only resume if the checkpoint is within the requested range. Otherwise, e.g. for a backfill
+    // below the checkpoint, all blocks of the range are indexed.
+    let start_block_height = match last_block_height {
+        Some(h) if h >= from_block && h < to_block => {
+            db.resume_after(h);
+            h + 1
+        }
+        _ => from_block,
+    };
+    tracing::log::info!(target: PROJECT_ID, "Starting from {}", start_block_height);
+
+    let stream = block_source.streamer(start_block_height, is_running);
+    listen_blocks(stream, &mut db).await;
+}
+
+/// Splits the blocks into ranges of `RANGE_SIZE` blocks and processes them with `num_workers`
+/// workers. Every range has its own checkpoint, so completed ranges are skipped on restart. The
+/// ranges are aligned to `FROM_BLOCK`, so it should stay the same between restarts.
+async fn backfill_in_parallel(
+    from_block: BlockHeight,
+    to_block: BlockHeight,
+    block_source: BlockSource,
+    num_workers: usize,
+    is_running: Arc<AtomicBool>,
+) {
+    let range_size: BlockHeight = env::var("RANGE_SIZE")
+        .map(|s| s.parse().expect("Invalid RANGE_SIZE"))
+        .unwrap_or(DEFAULT_RANGE_SIZE);
+    assert!(range_size > 0, "RANGE_SIZE must be positive");
+    let ranges = (from_block..to_block)
+        .step_by(range_size as usize)
+        .map(|range_start| (range_start, (range_start + range_size).min(to_block)))
+        .collect::<VecDeque<_>>();
+    tracing::log::info!(target: PROJECT_ID, "Processing {} ranges of {} blocks with {} workers", ranges.len(), range_size, num_workers);
+    let ranges = Arc::new(Mutex::new(ranges));
+    let block_source = Arc::new(block_source);
+    let workers = (0..num_workers)
+        .map(|worker_id| {
+            tokio::spawn(run_worker(
+                worker_id,
+                ranges.clone(),
+                block_source.clone(),
+                is_running.clone(),
+            ))
         })
-        .collect()
+        .collect::<Vec<_>>();
+    for worker in workers {
+        worker.await.unwrap();
+    }
 }
 
-pub async fn start(config: Config, blocks_sink: mpsc::Sender<StreamerMessage>) {
-    let mut shards = read_folder(&config.path);
-    assert!(
-        shards.contains(&BLOCK_FOLDER.to_string()),
-        "Block folder not found"
-    );
-    shards.retain(|s| s != BLOCK_FOLDER);
-    let mut current_block_file = "".to_string();
-    let mut block_entries = HashMap::new();
-    let mut shard_entries = vec![];
-
-    for block_height in config.from_block..config.to_block {
-        tracing::log::debug!(target: PROJECT_ID, "Processing block: {}", block_height);
-        let rounded_padded_block_height = format!("{:0>12}", block_height / 5 * 5);
-        let padded_block_height = format!("{:0>12}", block_height);
-        let expected_sub_path = format!(
-            "{}/{}/{}.tgz",
-            &rounded_padded_block_height[..6],
-            &rounded_padded_block_height[6..9],
-            rounded_padded_block_height
-        );
-        let block_path = format!("{}/{}/{}", config.path, BLOCK_FOLDER, expected_sub_path);
-        if block_path != current_block_file {
-            tracing::log::debug!(target: PROJECT_ID, "Reading block: {}", block_path);
-            current_block_file = block_path;
-            block_entries = read_archive(&current_block_file);
-            shard_entries = shards
-                .iter()
-                .map(|shard_id| {
-                    let shard_path = format!("{}/{}/{}", config.path, shard_id, expected_sub_path);
-                    read_archive(&shard_path)
-                })
-                .collect();
-        }
-        let block_str = block_entries.get(&format!("{}.json", padded_block_height));
-        if block_str.is_none() {
-            tracing::log::debug!(target: PROJECT_ID, "Block not found: {}", block_height);
+async fn run_worker(
+    worker_id: usize,
+    ranges: Arc<Mutex<VecDeque<(BlockHeight, BlockHeight)>>>,
+    block_source: Arc<BlockSource>,
+    is_running: Arc<AtomicBool>,
+) {
+    while is_running.load(Ordering::SeqCst) {
+        let Some((range_start, range_end)) = ranges.lock().unwrap().pop_front() else {
+            break;
+        };
+        let sink = format!("{}_{}_{}", PROJECT_ID, range_start, range_end);
+        let mut db = ClickDB::new(&sink, PROJECT_ID, MIN_BATCH);
+        if is_range_completed(&db, range_end)
+            .await
+            .expect("Failed to get the range completion from clickhouse")
+        {
+            tracing::log::info!(target: PROJECT_ID, "Worker {}: Range {}..{} is already completed", worker_id, range_start, range_end);
             continue;
         }
-        let block = serde_json::from_str(&block_str.unwrap()).unwrap();
-        let shards = shard_entries
-            .iter()
-            .filter_map(|shard| shard.get(&format!("{}.json", padded_block_height)))
-            .map(|s| serde_json::from_str(s).unwrap())
-            .collect::<Vec<_>>();
-        let streamer_message = StreamerMessage { block, shards };
-        blocks_sink.send(streamer_message).await.unwrap();
+        let last_block_height = db
+            .load_checkpoint()
+            .await
+            .expect("Failed to get the checkpoint from clickhouse");
+        let start_block_height = match last_block_height {
+            Some(h) if h >= range_start => {
+                db.resume_after(h);
+                h + 1
+            }
+            _ => range_start,
+        };
+        tracing::log::info!(target: PROJECT_ID, "Worker {}: Processing range {}..{} starting from {}", worker_id, range_start, range_end, start_block_height);
+        let (stream, handle) = range_source(&block_source, range_end)
+            .spawn_streamer(start_block_height, is_running.clone());
+        listen_blocks(stream, &mut db).await;
+        // The stream also ends on Ctrl+C, or if the source failed, and then the range is incomplete.
+        handle.await.unwrap_or_else(|err| {
+            panic!(
+                "Failed to stream range {}..{}: {}",
+                range_start, range_end, err
+            )
+        });
+        if !is_running.load(Ordering::SeqCst) {
+            break;
+        }
+        commit_range_completed(&mut db, range_end)
+            .await
+            .expect("Failed to commit the range completion");
+        tracing::log::info!(target: PROJECT_ID, "Worker {}: Completed range {}..{}", worker_id, range_start, range_end);
     }
 }
 
-pub fn streamer(config: Config) -> mpsc::Receiver<StreamerMessage> {
-    let (sender, receiver) = mpsc::channel(100);
-    actix::spawn(start(config, sender));
-    receiver
+/// Loads whether all blocks before the given block height were committed, as recorded by
+/// `commit_range_completed`.
+async fn is_range_completed(
+    db: &ClickDB,
+    end_block_height: BlockHeight,
+) -> clickhouse::error::Result<bool> {
+    let completed_block_height = db
+        .client
+        .query("SELECT block_height FROM checkpoints WHERE sink = ? ORDER BY block_height DESC LIMIT 1")
+        .bind(completed_sink(db))
+        .fetch_optional::<u64>()
+        .await?;
+    Ok(completed_block_height
+        .map(|block_height| block_height >= end_block_height)
+        .unwrap_or(false))
 }
 
-fn main() {
-    openssl_probe::init_ssl_cert_env_vars();
-    dotenv().ok();
-
-    common::setup_tracing("clickhouse=info,lake_indexer=info");
-
-    tracing::log::info!(target: PROJECT_ID, "Starting NEAR Lake Indexer");
-
-    let db = ClickDB::new(20_000);
-
-    let config = Config {
-        from_block: env::var("FROM_BLOCK")
-            .expect("FROM_BLOCK is required")
-            .parse()
-            .unwrap(),
-        to_block: env::var("TO_BLOCK")
-            .expect("TO_BLOCK (exclusive) is required")
-            .parse()
-            .unwrap(),
-        path: env::var("LAKE_DATA_PATH").unwrap(),
+/// Records that all blocks before the given block height were committed, when the range was fully
+/// streamed. The last checkpoint alone can't tell that, because the last blocks of a range may be
+/// missing. It's stored as a checkpoint of the `<sink>_completed` sink without a block hash.
+async fn commit_range_completed(
+    db: &mut ClickDB,
+    end_block_height: BlockHeight,
+) -> clickhouse::error::Result<()> {
+    db.commit().await?;
+    let row = CheckpointRow {
+        sink: completed_sink(db),
+        block_height: end_block_height,
+        block_hash: String::new(),
+        block_timestamp: 0,
     };
+    insert_rows_with_retry(&db.client, &vec![row], "checkpoints").await
+}
 
-    let sys = actix::System::new();
-    sys.block_on(async move {
-        let stream = streamer(config);
-        listen_blocks(stream, db).await;
+fn completed_sink(db: &ClickDB) -> String {
+    format!("{}_completed", db.sink)
+}
 
-        actix::System::current().stop();
-    });
-    sys.run().unwrap();
+/// Returns the same source, but ending at the given block height.
+fn range_source(block_source: &BlockSource, to_block: BlockHeight) -> BlockSource {
+    match block_source {
+        BlockSource::Lake(config) => BlockSource::Lake(LakeConfig {
+            path: config.path.clone(),
+            to_block,
+        }),
+        BlockSource::Archive(config) => BlockSource::Archive(ArchiveConfig {
+            path: config.path.clone(),
+            to_block,
+        }),
+        _ => unreachable!("Only the sources with TO_BLOCK can be split into ranges"),
+    }
 }
 
-async fn listen_blocks(mut stream: mpsc::Receiver<StreamerMessage>, mut db: ClickDB) {
-    while let Some(streamer_message) = stream.recv().await {
-        tracing::log::debug!(target: PROJECT_ID, "Received streamer message: {:?}", streamer_message);
-        extract_info(&mut db, streamer_message).await.unwrap();
+async fn listen_blocks(mut stream: mpsc::Receiver<BlockWithTxHashes>, db: &mut ClickDB) {
+    let mut interval = tokio::time::interval(FLUSH_CHECK_INTERVAL);
+    loop {
+        tokio::select! {
+            block = stream.recv() => {
+                let Some(block) = block else {
+                    break;
+                };
+                tracing::log::debug!(target: PROJECT_ID, "Received block: {}", block.block.header.height);
+                extract_info(db, block).await.unwrap();
+            }
+            _ = interval.tick() => {
+                db.flush_if_stale().await.unwrap();
+            }
+        }
     }
     db.commit().await.unwrap();
 }
@@ -1,108 +1,112 @@
 mod common;
+mod lake_reader;
 mod xblock;
 
+use crate::lake_reader::{LakeReader, GROUP_SIZE};
+use crate::xblock::codec::{ArchiveCodec, EncoderConfig};
+use crate::xblock::manifest::{
+    create_atomically, is_archive_complete, manifest_path, sha256_hex, ArchiveManifest,
+    ManifestBlock, Sha256Writer,
+};
+use crate::xblock::reader::{archive_height, archive_path, read_archive, ARCHIVE_SIZE};
 use crate::xblock::XBlock;
 use dotenv::dotenv;
-use flate2::read::GzDecoder;
-use near_indexer::near_primitives::borsh::BorshSerialize;
-use near_indexer::near_primitives::types::BlockHeight;
-use std::collections::HashMap;
-use std::io::Read;
+use fastnear_primitives::near_primitives::types::BlockHeight;
 use std::{env, fs};
 use tokio::sync::mpsc;
 
 const PROJECT_ID: &str = "lake_convert";
-const BLOCK_FOLDER: &str = "block";
-const SAVE_EVERY_N: BlockHeight = 1000;
 
 pub struct Config {
-    pub from_block: BlockHeight,
-    pub to_block: BlockHeight,
+    /// The ranges of blocks to convert, aligned to the archives. Exclusive.
+    pub ranges: Vec<(BlockHeight, BlockHeight)>,
     pub path: String,
 }
 
 pub struct WriterConfig {
     pub path: String,
+    pub encoder: EncoderConfig,
 }
 
-fn read_folder(path: &str) -> Vec<String> {
-    let mut entries: Vec<String> = fs::read_dir(path)
-        .unwrap()
-        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
-        .collect();
-    entries.sort();
-    entries
+/// The blocks of one archive, starting at the archive height. The lake may have no blocks in it.
+pub type Archive = (BlockHeight, Vec<XBlock>);
+
+pub async fn start(config: Config, archives_sink: mpsc::Sender<Archive>) {
+    for (range_start, range_end) in config.ranges {
+        tracing::log::info!(target: PROJECT_ID, "Converting blocks from {} to {}", range_start, range_end);
+        let mut reader = LakeReader::new(&config.path, range_start, range_end)
+            .await
+            .expect("Failed to open the lake");
+        let mut archive: Archive = (range_start, vec![]);
+        while let Some((group_height, group)) =
+            reader.next_group().await.expect("Failed to read the lake")
+        {
+            let from_block = group_height.max(range_start);
+            let to_block = (group_height + GROUP_SIZE).min(range_end);
+            for block_height in from_block..to_block {
+                send_archives_before(&archives_sink, &mut archive, block_height).await;
+                tracing::log::debug!(target: PROJECT_ID, "Processing block: {}", block_height);
+                let Some((block_str, shard_strs)) = group.block(block_height) else {
+                    tracing::log::debug!(target: PROJECT_ID, "Block not found: {}", block_height);
+                    continue;
+                };
+                let block = serde_json::from_str(block_str).unwrap();
+                let shards = shard_strs
+                    .into_iter()
+                    .map(|s| serde_json::from_str(s).unwrap())
+                    .collect::<Vec<_>>();
+                archive.1.push(XBlock { block, shards });
+            }
+        }
+        send_archives_before(&archives_sink, &mut archive, range_end).await;
+    }
 }
 
-fn read_archive(path: &str) -> HashMap<String, String> {
-    if !std::path::Path::new(path).exists() {
-        return HashMap::new();
-    }
-    tar::Archive::new(GzDecoder::new(std::fs::File::open(path).unwrap()))
-        .entries()
-        .unwrap()
-        .filter_map(|e| e.ok())
-        .map(|mut e| {
-            let path = e.path().unwrap().to_string_lossy().to_string();
-            let mut content = String::new();
-            e.read_to_string(&mut content).unwrap();
-            (path, content)
-        })
-        .collect()
+/// Sends the archives ending at or before the given block height, including the ones without
+/// blocks, so every archive of the range gets a manifest and isn't read again on restart.
+async fn send_archives_before(
+    archives_sink: &mpsc::Sender<Archive>,
+    archive: &mut Archive,
+    block_height: BlockHeight,
+) {
+    while archive.0 + ARCHIVE_SIZE <= block_height {
+        let next_archive = (archive.0 + ARCHIVE_SIZE, vec![]);
+        archives_sink
+            .send(std::mem::replace(archive, next_archive))
+            .await
+            .unwrap();
+    }
 }
 
-pub async fn start(config: Config, blocks_sink: mpsc::Sender<XBlock>) {
-    let mut shards = read_folder(&config.path);
-    assert!(
-        shards.contains(&BLOCK_FOLDER.to_string()),
-        "Block folder not found"
-    );
-    shards.retain(|s| s != BLOCK_FOLDER);
-    let mut current_block_file = "".to_string();
-    let mut block_entries = HashMap::new();
-    let mut shard_entries = vec![];
-
-    for block_height in config.from_block..config.to_block {
-        tracing::log::debug!(target: PROJECT_ID, "Processing block: {}", block_height);
-        let rounded_padded_block_height = format!("{:0>12}", block_height / 5 * 5);
-        let padded_block_height = format!("{:0>12}", block_height);
-        let expected_sub_path = format!(
-            "{}/{}/{}.tgz",
-            &rounded_padded_block_height[..6],
-            &rounded_padded_block_height[6..9],
-            rounded_padded_block_height
-        );
-        let block_path = format!("{}/{}/{}", config.path, BLOCK_FOLDER, expected_sub_path);
-        if block_path != current_block_file {
-            tracing::log::debug!(target: PROJECT_ID, "Reading block: {}", block_path);
-            current_block_file = block_path;
-            block_entries = read_archive(&current_block_file);
-            shard_entries = shards
-                .iter()
-                .map(|shard_id| {
-                    let shard_path = format!("{}/{}/{}", config.path, shard_id, expected_sub_path);
-                    read_archive(&shard_path)
-                })
-                .collect();
-        }
-        let block_str = block_entries.get(&format!("{}.json", padded_block_height));
-        if block_str.is_none() {
-            tracing::log::debug!(target: PROJECT_ID, "Block not found: {}", block_height);
+/// Returns the ranges of the archives that have to be converted. Complete archives are skipped,
+/// unless `overwrite` is set, so an interrupted conversion can be restarted with the same range.
+fn ranges_to_convert(
+    path: &str,
+    from_block: BlockHeight,
+    to_block: BlockHeight,
+    overwrite: bool,
+) -> Vec<(BlockHeight, BlockHeight)> {
+    let mut ranges: Vec<(BlockHeight, BlockHeight)> = vec![];
+    let mut num_complete = 0;
+    for archive_height in (from_block..to_block).step_by(ARCHIVE_SIZE as usize) {
+        if !overwrite && is_archive_complete(path, archive_height) {
+            num_complete += 1;
             continue;
         }
-        let block = serde_json::from_str(&block_str.unwrap()).unwrap();
-        let shards = shard_entries
-            .iter()
-            .filter_map(|shard| shard.get(&format!("{}.json", padded_block_height)))
-            .map(|s| serde_json::from_str(s).unwrap())
-            .collect::<Vec<_>>();
-        let streamer_message = XBlock { block, shards };
-        blocks_sink.send(streamer_message).await.unwrap();
+        let archive_end = archive_height + ARCHIVE_SIZE;
+        match ranges.last_mut() {
+            Some((_, range_end)) if *range_end == archive_height => *range_end = archive_end,
+            _ => ranges.push((archive_height, archive_end)),
+        }
+    }
+    if num_complete > 0 {
+        tracing::log::info!(target: PROJECT_ID, "Skipping {} complete archives", num_complete);
     }
+    ranges
 }
 
-pub fn streamer(config: Config) -> mpsc::Receiver<XBlock> {
-    let (sender, receiver) = mpsc::channel(100);
+pub fn streamer(config: Config) -> mpsc::Receiver<Archive> {
+    let (sender, receiver) = mpsc::channel(1);
     actix::spawn(start(config, sender));
     receiver
 }
@@ -111,29 +115,61 @@ fn main() {
     openssl_probe::init_ssl_cert_env_vars();
     dotenv().ok();
 
-    common::setup_tracing("lake_convert=info");
+    common::setup_tracing("lake_convert=info,lake_reader=info");
 
-    tracing::log::info!(target: PROJECT_ID, "Starting NEAR Lake Indexer");
+    let args: Vec<String> = env::args().collect();
+    let is_verify = args.get(1).map(|arg| arg.as_str()) == Some("verify");
 
-    let config = Config {
-        from_block: env::var("FROM_BLOCK")
+    let from_block = archive_height(
+        env::var("FROM_BLOCK")
             .expect("FROM_BLOCK is required")
             .parse::<BlockHeight>()
-            .unwrap()
-            / SAVE_EVERY_N
-            * SAVE_EVERY_N,
-        to_block: env::var("TO_BLOCK")
+            .unwrap(),
+    );
+    let to_block = archive_height(
+        env::var("TO_BLOCK")
             .expect("TO_BLOCK (exclusive) is required")
             .parse::<BlockHeight>()
-            .unwrap()
-            / SAVE_EVERY_N
-            * SAVE_EVERY_N,
+            .unwrap(),
+    );
+    let write_path = env::var("WRITE_DATA_PATH").expect("Missing env WRITE_DATA_PATH");
+
+    if is_verify {
+        tracing::log::info!(target: PROJECT_ID, "Verifying archives in {} from {} to {}", write_path, from_block, to_block);
+        let errors = verify(&write_path, from_block, to_block);
+        for error in &errors {
+            tracing::log::error!(target: PROJECT_ID, "{}", error);
+        }
+        if !errors.is_empty() {
+            tracing::log::error!(target: PROJECT_ID, "Found {} errors", errors.len());
+            std::process::exit(1);
+        }
+        tracing::log::info!(target: PROJECT_ID, "All archives are valid");
+        return;
+    }
+
+    tracing::log::info!(target: PROJECT_ID, "Starting NEAR Lake Indexer");
+
+    let overwrite: bool = env::var("OVERWRITE")
+        .map(|s| s.parse().expect("Invalid OVERWRITE"))
+        .unwrap_or(false);
+    let config = Config {
+        ranges: ranges_to_convert(&write_path, from_block, to_block, overwrite),
         path: env::var("LAKE_DATA_PATH").unwrap(),
     };
 
+    if std::path::Path::new(&write_path).exists() {
+        remove_temporary_files(std::path::Path::new(&write_path))
+            .expect("Failed to remove the temporary files");
+    }
     let writer_config = WriterConfig {
-        path: env::var("WRITE_DATA_PATH").expect("Missing env WRITE_DATA_PATH"),
+        path: write_path,
+        encoder: EncoderConfig::from_env(),
     };
+    writer_config
+        .encoder
+        .save_dictionary(&writer_config.path)
+        .expect("Failed to save the zstd dictionary");
 
     let sys = actix::System::new();
     sys.block_on(async move {
@@ -145,52 +181,414 @@ fn main() {
     sys.run().unwrap();
 }
 
-fn save_blocks(blocks: &[XBlock], config: &WriterConfig) -> std::io::Result<()> {
-    if blocks.is_empty() {
-        return Ok(());
+fn save_blocks(
+    archive_height: BlockHeight,
+    blocks: &[XBlock],
+    config: &WriterConfig,
+) -> std::io::Result<()> {
+    let filename = archive_path(&config.path, archive_height, config.encoder.codec);
+    fs::create_dir_all(std::path::Path::new(&filename).parent().unwrap())?;
+    // Removing the manifest first, so the archive isn't considered complete until it's rewritten,
+    // and the archives of the other codecs, so they can't be read instead of the new one.
+    let manifest_path = manifest_path(&config.path, archive_height);
+    if std::path::Path::new(&manifest_path).exists() {
+        fs::remove_file(&manifest_path)?;
+    }
+    for codec in ArchiveCodec::ALL {
+        let other_filename = archive_path(&config.path, archive_height, codec);
+        if codec != config.encoder.codec && std::path::Path::new(&other_filename).exists() {
+            fs::remove_file(&other_filename)?;
+        }
     }
-    let starting_block = blocks[0].block.header.height / SAVE_EVERY_N * SAVE_EVERY_N;
-    let padded_block_height = format!("{:0>12}", starting_block);
-    let path = format!("{}/{}", config.path, &padded_block_height[..6]);
-    fs::create_dir_all(&path)?;
-    let filename = format!("{}/{}.tgz", path, padded_block_height);
     tracing::log::info!(target: PROJECT_ID, "Saving blocks to: {}", filename);
-    // Creating archive
-    let tar_gz = fs::File::create(&filename)?;
-    let enc = flate2::write::GzEncoder::new(tar_gz, flate2::Compression::default());
-    let mut tar = tar::Builder::new(enc);
-    for block in blocks {
-        let block_str = block.try_to_vec().unwrap();
-        let padded_block_height = format!("{:0>12}", block.block.header.height);
-        let mut header = tar::Header::new_gnu();
-        header.set_path(&format!("{}.borsh", padded_block_height))?;
-        header.set_size(block_str.len() as u64);
-        header.set_cksum();
-        tar.append(&header, &block_str[..])?;
-    }
-
-    tar.into_inner()?.finish()?;
+    // The tar is streamed through the encoder into the file, hashing the compressed content.
+    let (sha256, size) = create_atomically(&filename, |file| {
+        let mut tar = tar::Builder::new(config.encoder.encoder(Sha256Writer::new(file))?);
+        for block in blocks {
+            let block_str = borsh::to_vec(block).unwrap();
+            let padded_block_height = format!("{:0>12}", block.block.header.height);
+            let mut header = tar::Header::new_gnu();
+            header.set_path(&format!("{}.borsh", padded_block_height))?;
+            header.set_size(block_str.len() as u64);
+            header.set_cksum();
+            tar.append(&header, &block_str[..])?;
+        }
+        Ok(tar.into_inner()?.finish()?.finish())
+    })?;
+    ArchiveManifest::new(archive_height, config.encoder.codec, sha256, size, blocks)
+        .save(&config.path)?;
     Ok(())
 }
 
-async fn listen_blocks(mut stream: mpsc::Receiver<XBlock>, config: WriterConfig) {
-    let mut blocks: Vec<XBlock> = vec![];
-    while let Some(xblock) = stream.recv().await {
-        let block_height = xblock.block.header.height;
-        if blocks.get(0).map(|b| b.block.header.height).unwrap_or(0) / SAVE_EVERY_N
-            != block_height / SAVE_EVERY_N
+async fn listen_blocks(mut stream: mpsc::Receiver<Archive>, config: WriterConfig) {
+    while let Some((archive_height, blocks)) = stream.recv().await {
+        save_blocks(archive_height, &blocks, &config).expect("Failed to save blocks");
+    }
+}
+
+/// Removes the temporary files left in the folder and its subfolders by an interrupted conversion.
+fn remove_temporary_files(path: &std::path::Path) -> std::io::Result<()> {
+    for entry in fs::read_dir(path)? {
+        let entry_path = entry?.path();
+        if entry_path.is_dir() {
+            remove_temporary_files(&entry_path)?;
+        } else if entry_path
+            .extension()
+            .is_some_and(|extension| extension == "tmp")
         {
-            save_blocks(&blocks, &config).expect("Failed to save blocks");
-            blocks.clear();
+            tracing::log::info!(target: PROJECT_ID, "Removing temporary file: {}", entry_path.display());
+            fs::remove_file(entry_path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Checks the archives from `from_block` to `to_block` against their manifests and the chain
+/// continuity across them: every block has to link to the previous block with `prev_hash`. Returns
+/// the errors found.
+fn verify(path: &str, from_block: BlockHeight, to_block: BlockHeight) -> Vec<String> {
+    let mut errors = vec![];
+    let mut error = |message: String| errors.push(message);
+    let mut prev_block: Option<ManifestBlock> = None;
+    for archive_height in (from_block..to_block).step_by(ARCHIVE_SIZE as usize) {
+        tracing::log::debug!(target: PROJECT_ID, "Verifying archive: {}", archive_height);
+        let manifest = match ArchiveManifest::load(path, archive_height) {
+            Ok(Some(manifest)) => manifest,
+            Ok(None) => {
+                error(format!("Archive {}: Missing manifest", archive_height));
+                prev_block = None;
+                continue;
+            }
+            Err(err) => {
+                error(format!(
+                    "Archive {}: Invalid manifest: {}",
+                    archive_height, err
+                ));
+                prev_block = None;
+                continue;
+            }
+        };
+
+        let filename = archive_path(path, archive_height, manifest.codec);
+        match fs::read(&filename) {
+            Ok(content) => {
+                if content.len() as u64 != manifest.size || sha256_hex(&content) != manifest.sha256
+                {
+                    error(format!("Archive {}: Checksum mismatch", archive_height));
+                }
+            }
+            Err(err) => error(format!(
+                "Archive {}: Failed to read {}: {}",
+                archive_height, filename, err
+            )),
+        }
+        match read_archive(path, archive_height) {
+            Ok(Some(blocks)) => {
+                let blocks = blocks.iter().map(ManifestBlock::from).collect::<Vec<_>>();
+                if blocks != manifest.blocks {
+                    error(format!(
+                        "Archive {}: The blocks don't match the manifest",
+                        archive_height
+                    ));
+                }
+            }
+            Ok(None) => error(format!("Archive {}: Missing archive", archive_height)),
+            Err(err) => error(format!(
+                "Archive {}: Failed to decode: {}",
+                archive_height, err
+            )),
+        }
+
+        for block in manifest.blocks {
+            if !(archive_height..archive_height + ARCHIVE_SIZE).contains(&block.height) {
+                error(format!(
+                    "Archive {}: Block {} is out of range",
+                    archive_height, block.height
+                ));
+            }
+            if let Some(prev_block) = &prev_block {
+                if block.height <= prev_block.height {
+                    error(format!(
+                        "Block {}: Not after block {}",
+                        block.height, prev_block.height
+                    ));
+                } else if block.prev_hash != prev_block.hash {
+                    error(format!(
+                        "Block {}: prev_hash {} doesn't match block {} with hash {}. Missing blocks in between",
+                        block.height, block.prev_hash, prev_block.height, prev_block.hash
+                    ));
+                }
+            }
+            prev_block = Some(block);
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xblock::reader::find_archive;
+    use fastnear_primitives::near_primitives::hash::CryptoHash;
+
+    const FIXTURE: &str = include_str!("../../tests/fixtures/streamer_message.json");
+
+    /// Returns the fixture block at each of the given heights, each linked to the previous one.
+    fn chained_blocks(heights: impl IntoIterator<Item = BlockHeight>) -> Vec<XBlock> {
+        let mut prev_hash = CryptoHash::default();
+        heights
+            .into_iter()
+            .map(|height| {
+                let mut block: XBlock = serde_json::from_str(FIXTURE).unwrap();
+                block.block.header.height = height;
+                block.block.header.prev_hash = prev_hash;
+                block.block.header.hash = CryptoHash::hash_bytes(&height.to_le_bytes());
+                prev_hash = block.block.header.hash;
+                block
+            })
+            .collect()
+    }
+
+    fn writer_config(path: &str, codec: ArchiveCodec) -> WriterConfig {
+        WriterConfig {
+            path: path.to_string(),
+            encoder: EncoderConfig {
+                codec,
+                zstd_level: 3,
+                zstd_dictionary: None,
+            },
         }
+    }
+
+    fn assert_round_trip(config: &WriterConfig) {
+        let blocks = chained_blocks([120000000, 120000001, 120000005]);
+        save_blocks(120000000, &blocks, config).unwrap();
+        let read_blocks = read_archive(&config.path, 120000000).unwrap().unwrap();
+        assert_eq!(
+            read_blocks
+                .iter()
+                .map(|block| borsh::to_vec(block).unwrap())
+                .collect::<Vec<_>>(),
+            blocks
+                .iter()
+                .map(|block| borsh::to_vec(block).unwrap())
+                .collect::<Vec<_>>()
+        );
+        assert!(is_archive_complete(&config.path, 120000000));
+        let manifest = ArchiveManifest::load(&config.path, 120000000)
+            .unwrap()
+            .unwrap();
+        let content =
+            fs::read(archive_path(&config.path, 120000000, config.encoder.codec)).unwrap();
+        assert_eq!(manifest.codec, config.encoder.codec);
+        assert_eq!(manifest.size, content.len() as u64);
+        assert_eq!(manifest.sha256, sha256_hex(&content));
+    }
+
+    #[test]
+    fn test_round_trip_zstd() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_round_trip(&writer_config(
+            dir.path().to_str().unwrap(),
+            ArchiveCodec::Zstd,
+        ));
+    }
+
+    #[test]
+    fn test_round_trip_zstd_with_dictionary() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let mut config = writer_config(path, ArchiveCodec::Zstd);
+        // A raw content dictionary, the blocks are similar to the fixture.
+        config.encoder.zstd_dictionary = Some(FIXTURE.as_bytes().to_vec());
+        config.encoder.save_dictionary(path).unwrap();
+        assert_round_trip(&config);
+    }
+
+    #[test]
+    fn test_round_trip_gzip() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_round_trip(&writer_config(
+            dir.path().to_str().unwrap(),
+            ArchiveCodec::Gzip,
+        ));
+    }
+
+    #[test]
+    fn test_round_trip_none() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_round_trip(&writer_config(
+            dir.path().to_str().unwrap(),
+            ArchiveCodec::None,
+        ));
+    }
+
+    fn save_archives(path: &str, blocks: &[XBlock]) {
+        let config = writer_config(path, ArchiveCodec::Gzip);
+        for archive in blocks.chunk_by(|a, b| {
+            archive_height(a.block.header.height) == archive_height(b.block.header.height)
+        }) {
+            save_blocks(
+                archive_height(archive[0].block.header.height),
+                archive,
+                &config,
+            )
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_verify_valid_archives() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        save_archives(path, &chained_blocks([120000000, 120000999, 120001000]));
+        assert_eq!(verify(path, 120000000, 120002000), Vec::<String>::new());
+    }
 
-        tracing::log::debug!(target: PROJECT_ID, "Processing block: {}", block_height);
-        //
-        // let v = serde_json::to_vec(&streamer_message).unwrap();
-        // let xblock: XBlock = serde_json::from_slice(&v).expect("Failed to deserialize");
-        blocks.push(xblock);
+    #[test]
+    fn test_verify_checksum_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        save_archives(path, &chained_blocks([120000000, 120000001]));
+        let filename = archive_path(path, 120000000, ArchiveCodec::Gzip);
+        let mut content = fs::read(&filename).unwrap();
+        // The gzip header's modification time, so the archive still decodes.
+        content[4] ^= 1;
+        fs::write(&filename, content).unwrap();
+        assert_eq!(
+            verify(path, 120000000, 120001000),
+            vec!["Archive 120000000: Checksum mismatch"]
+        );
+    }
+
+    #[test]
+    fn test_verify_missing_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        save_archives(path, &chained_blocks([120000000, 120001000]));
+        fs::remove_file(manifest_path(path, 120001000)).unwrap();
+        assert_eq!(
+            verify(path, 120000000, 120002000),
+            vec!["Archive 120001000: Missing manifest"]
+        );
+    }
+
+    #[test]
+    fn test_verify_broken_prev_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let mut blocks = chained_blocks([120000000, 120000999]);
+        // The block 120001000 links to a block that's not in the archives.
+        blocks.extend(chained_blocks([120000998, 120001000]).pop());
+        save_archives(path, &blocks);
+        let errors = verify(path, 120000000, 120002000);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with(&format!(
+            "Block 120001000: prev_hash {} doesn't match block 120000999 with hash {}",
+            CryptoHash::hash_bytes(&120000998u64.to_le_bytes()),
+            blocks[1].block.header.hash
+        )));
     }
-    if !blocks.is_empty() {
-        save_blocks(&blocks, &config).expect("Failed to save blocks");
+
+    #[test]
+    fn test_verify_out_of_range_block() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        save_archives(path, &chained_blocks([120000000, 120000001]));
+        let mut manifest = ArchiveManifest::load(path, 120000000).unwrap().unwrap();
+        manifest.blocks[1].height = 120001001;
+        manifest.save(path).unwrap();
+        assert_eq!(
+            verify(path, 120000000, 120001000),
+            vec![
+                "Archive 120000000: The blocks don't match the manifest",
+                "Archive 120000000: Block 120001001 is out of range",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ranges_to_convert() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        save_archives(path, &chained_blocks([120000000, 120002000, 120003000]));
+        // An interrupted conversion of the archive 120003000.
+        fs::remove_file(manifest_path(path, 120003000)).unwrap();
+        assert_eq!(
+            ranges_to_convert(path, 120000000, 120005000, false),
+            vec![(120001000, 120002000), (120003000, 120005000)]
+        );
+        assert_eq!(
+            ranges_to_convert(path, 120000000, 120005000, true),
+            vec![(120000000, 120005000)]
+        );
+        assert_eq!(ranges_to_convert(path, 120000000, 120001000, false), vec![]);
+    }
+
+    #[test]
+    fn test_overwrite_removes_other_codecs() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let blocks = chained_blocks([120000000, 120000001]);
+        save_blocks(120000000, &blocks, &writer_config(path, ArchiveCodec::Gzip)).unwrap();
+        save_blocks(120000000, &blocks, &writer_config(path, ArchiveCodec::Zstd)).unwrap();
+        assert!(!std::path::Path::new(&archive_path(path, 120000000, ArchiveCodec::Gzip)).exists());
+        assert_eq!(
+            find_archive(path, 120000000),
+            Some((
+                archive_path(path, 120000000, ArchiveCodec::Zstd),
+                ArchiveCodec::Zstd
+            ))
+        );
+        assert_eq!(read_archive(path, 120000000).unwrap().unwrap().len(), 2);
+        assert_eq!(verify(path, 120000000, 120001000), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_archive_without_blocks_is_complete() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        save_blocks(120000000, &[], &writer_config(path, ArchiveCodec::Gzip)).unwrap();
+        assert!(is_archive_complete(path, 120000000));
+        assert_eq!(ranges_to_convert(path, 120000000, 120001000, false), vec![]);
+        assert!(read_archive(path, 120000000).unwrap().unwrap().is_empty());
+        assert_eq!(verify(path, 120000000, 120001000), Vec::<String>::new());
+    }
+
+    #[tokio::test]
+    async fn test_send_archives_before() {
+        let (sender, mut receiver) = mpsc::channel(10);
+        let mut archive: Archive = (120000000, chained_blocks([120000000]));
+        send_archives_before(&sender, &mut archive, 120000999).await;
+        assert!(receiver.try_recv().is_err());
+        // The archive 120001000 is sent without blocks.
+        send_archives_before(&sender, &mut archive, 120002000).await;
+        let (first_height, first_blocks) = receiver.try_recv().unwrap();
+        assert_eq!((first_height, first_blocks.len()), (120000000, 1));
+        let (second_height, second_blocks) = receiver.try_recv().unwrap();
+        assert_eq!((second_height, second_blocks.len()), (120001000, 0));
+        assert!(receiver.try_recv().is_err());
+        assert_eq!((archive.0, archive.1.len()), (120002000, 0));
+    }
+
+    #[test]
+    fn test_remove_temporary_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        save_archives(path, &chained_blocks([120000000]));
+        let archive_file = archive_path(path, 120000000, ArchiveCodec::Gzip);
+        let tmp_files = [
+            format!("{}.tmp", archive_file),
+            format!("{}.tmp", manifest_path(path, 120000000)),
+            format!("{}/zstd.dict.tmp", path),
+        ];
+        for tmp_file in &tmp_files {
+            fs::write(tmp_file, b"partial").unwrap();
+        }
+        remove_temporary_files(dir.path()).unwrap();
+        for tmp_file in &tmp_files {
+            assert!(!std::path::Path::new(tmp_file).exists());
+        }
+        assert!(is_archive_complete(path, 120000000));
     }
 }
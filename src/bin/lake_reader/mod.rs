@@ -0,0 +1,406 @@
+mod s3;
+
+use fastnear_primitives::near_primitives::types::BlockHeight;
+use flate2::read::GzDecoder;
+use s3::S3Client;
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::io::Read;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+
+const LAKE_READER_TARGET: &str = "lake_reader";
+const BLOCK_FOLDER: &str = "block";
+/// Every tgz archive contains this many blocks.
+pub const GROUP_SIZE: BlockHeight = 5;
+const DEFAULT_PREFETCH_GROUPS: usize = 10;
+
+/// The storage with the lake layout: a `block` folder and a folder per shard, each containing
+/// `<height / 10^6>/<height / 10^3 % 1000>/<height>.tgz` archives of 5 blocks.
+pub enum LakeStorage {
+    Local(String),
+    S3 { client: S3Client, prefix: String },
+}
+
+impl LakeStorage {
+    /// `s3://<bucket>/<prefix>` paths are read from S3, other paths from the local disk.
+    pub fn from_path(path: &str) -> Self {
+        match path.strip_prefix("s3://") {
+            Some(bucket_and_prefix) => {
+                let (bucket, prefix) = bucket_and_prefix
+                    .split_once('/')
+                    .unwrap_or((bucket_and_prefix, ""));
+                let prefix = prefix.trim_end_matches('/');
+                Self::S3 {
+                    client: S3Client::from_env(bucket),
+                    prefix: if prefix.is_empty() {
+                        "".to_string()
+                    } else {
+                        format!("{}/", prefix)
+                    },
+                }
+            }
+            None => Self::Local(path.trim_end_matches('/').to_string()),
+        }
+    }
+
+    async fn list_folders(&self) -> anyhow::Result<Vec<String>> {
+        let mut folders: Vec<String> = match self {
+            Self::Local(path) => {
+                let path = path.clone();
+                tokio::task::spawn_blocking(move || {
+                    std::fs::read_dir(path)?
+                        .map(|entry| Ok(entry?.file_name().to_string_lossy().to_string()))
+                        .collect::<std::io::Result<Vec<_>>>()
+                })
+                .await??
+            }
+            Self::S3 { client, prefix } => client.list_folders(prefix).await?,
+        };
+        folders.sort();
+        Ok(folders)
+    }
+
+    /// Returns the content of the file, or None if it doesn't exist.
+    async fn read(&self, sub_path: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        match self {
+            Self::Local(path) => {
+                let path = format!("{}/{}", path, sub_path);
+                let content = tokio::task::spawn_blocking(move || match std::fs::read(path) {
+                    Ok(content) => Ok(Some(content)),
+                    Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                    Err(err) => Err(err),
+                })
+                .await??;
+                Ok(content)
+            }
+            Self::S3 { client, prefix } => Ok(client
+                .get_object(&format!("{}{}", prefix, sub_path))
+                .await?),
+        }
+    }
+
+    async fn read_archive(&self, sub_path: &str) -> anyhow::Result<HashMap<String, String>> {
+        let Some(content) = self.read(sub_path).await? else {
+            return Ok(HashMap::new());
+        };
+        let entries = tokio::task::spawn_blocking(move || {
+            tar::Archive::new(GzDecoder::new(&content[..]))
+                .entries()?
+                .map(|entry| {
+                    let mut entry = entry?;
+                    let path = entry.path()?.to_string_lossy().to_string();
+                    let mut content = String::new();
+                    entry.read_to_string(&mut content)?;
+                    Ok((path, content))
+                })
+                .collect::<std::io::Result<HashMap<_, _>>>()
+        })
+        .await??;
+        Ok(entries)
+    }
+}
+
+/// The archives of a group of 5 blocks.
+pub struct LakeGroup {
+    block_entries: HashMap<String, String>,
+    shard_entries: Vec<HashMap<String, String>>,
+}
+
+impl LakeGroup {
+    /// Returns the block JSON and the JSONs of its shards, or None if the block is missing.
+    pub fn block(&self, block_height: BlockHeight) -> Option<(&String, Vec<&String>)> {
+        let file_name = format!("{:0>12}.json", block_height);
+        let block = self.block_entries.get(&file_name)?;
+        let shards = self
+            .shard_entries
+            .iter()
+            .filter_map(|shard| shard.get(&file_name))
+            .collect();
+        Some((block, shards))
+    }
+}
+
+/// Reads the lake groups in order, prefetching the next `LAKE_PREFETCH_GROUPS` groups in the
+/// background.
+pub struct LakeReader {
+    storage: Arc<LakeStorage>,
+    shards: Arc<Vec<String>>,
+    next_group_height: BlockHeight,
+    to_block: BlockHeight,
+    prefetch_groups: usize,
+    prefetched: VecDeque<(BlockHeight, JoinHandle<anyhow::Result<LakeGroup>>)>,
+}
+
+impl LakeReader {
+    pub async fn new(
+        path: &str,
+        from_block: BlockHeight,
+        to_block: BlockHeight,
+    ) -> anyhow::Result<Self> {
+        Self::from_storage(LakeStorage::from_path(path), from_block, to_block).await
+    }
+
+    pub async fn from_storage(
+        storage: LakeStorage,
+        from_block: BlockHeight,
+        to_block: BlockHeight,
+    ) -> anyhow::Result<Self> {
+        let mut shards = storage.list_folders().await?;
+        anyhow::ensure!(
+            shards.contains(&BLOCK_FOLDER.to_string()),
+            "Block folder not found"
+        );
+        shards.retain(|s| s != BLOCK_FOLDER);
+        Ok(Self {
+            storage: Arc::new(storage),
+            shards: Arc::new(shards),
+            next_group_height: from_block / GROUP_SIZE * GROUP_SIZE,
+            to_block,
+            prefetch_groups: env::var("LAKE_PREFETCH_GROUPS")
+                .map(|s| s.parse().expect("Invalid LAKE_PREFETCH_GROUPS"))
+                .unwrap_or(DEFAULT_PREFETCH_GROUPS)
+                .max(1),
+            prefetched: VecDeque::new(),
+        })
+    }
+
+    /// Returns the first block height of the next group with the group, or None after `to_block`.
+    pub async fn next_group(&mut self) -> anyhow::Result<Option<(BlockHeight, LakeGroup)>> {
+        while self.prefetched.len() < self.prefetch_groups && self.next_group_height < self.to_block
+        {
+            let group_height = self.next_group_height;
+            let storage = self.storage.clone();
+            let shards = self.shards.clone();
+            let handle = tokio::spawn(read_group(storage, shards, group_height));
+            self.prefetched.push_back((group_height, handle));
+            self.next_group_height += GROUP_SIZE;
+        }
+        let Some((group_height, handle)) = self.prefetched.pop_front() else {
+            return Ok(None);
+        };
+        Ok(Some((group_height, handle.await??)))
+    }
+}
+
+async fn read_group(
+    storage: Arc<LakeStorage>,
+    shards: Arc<Vec<String>>,
+    group_height: BlockHeight,
+) -> anyhow::Result<LakeGroup> {
+    let padded_group_height = format!("{:0>12}", group_height);
+    let expected_sub_path = format!(
+        "{}/{}/{}.tgz",
+        &padded_group_height[..6],
+        &padded_group_height[6..9],
+        padded_group_height
+    );
+    tracing::log::debug!(target: LAKE_READER_TARGET, "Reading group: {}", expected_sub_path);
+    let block_entries = storage
+        .read_archive(&format!("{}/{}", BLOCK_FOLDER, expected_sub_path))
+        .await?;
+    let mut shard_entries = vec![];
+    for shard_id in shards.iter() {
+        shard_entries.push(
+            storage
+                .read_archive(&format!("{}/{}", shard_id, expected_sub_path))
+                .await?,
+        );
+    }
+    Ok(LakeGroup {
+        block_entries,
+        shard_entries,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Returns a tgz archive with the given files.
+    fn tgz(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(vec![], flate2::Compression::fast()));
+        for (path, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            builder
+                .append_data(&mut header, path, content.as_bytes())
+                .unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    /// Starts a stand-in for an S3 server with the given responses by the request path and query,
+    /// and 404 for other requests. Returns the endpoint and the number of served requests.
+    async fn start_s3_server(
+        responses: HashMap<String, (u16, Vec<u8>)>,
+    ) -> (reqwest::Url, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let num_requests = Arc::new(AtomicUsize::new(0));
+        let server_num_requests = num_requests.clone();
+        let responses = Arc::new(responses);
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let responses = responses.clone();
+                server_num_requests.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let mut request = vec![];
+                    let mut buffer = [0u8; 1024];
+                    while !request.ends_with(b"\r\n\r\n") {
+                        let n = stream.read(&mut buffer).await.unwrap();
+                        if n == 0 {
+                            return;
+                        }
+                        request.extend_from_slice(&buffer[..n]);
+                    }
+                    let request = String::from_utf8(request).unwrap();
+                    assert!(request.contains("\r\nauthorization: AWS4-HMAC-SHA256 Credential=key/"));
+                    let target = request.split(' ').nth(1).unwrap();
+                    let (status, body) = responses
+                        .get(target)
+                        .cloned()
+                        .unwrap_or((404, b"<Error><Code>NoSuchKey</Code></Error>".to_vec()));
+                    let head = format!(
+                        "HTTP/1.1 {} Status\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        status,
+                        body.len()
+                    );
+                    stream.write_all(head.as_bytes()).await.unwrap();
+                    stream.write_all(&body).await.unwrap();
+                });
+            }
+        });
+        (endpoint.parse().unwrap(), num_requests)
+    }
+
+    fn s3_storage(endpoint: reqwest::Url) -> LakeStorage {
+        LakeStorage::S3 {
+            client: S3Client::new(endpoint, "us-east-1", "bucket", "key", "secret", None),
+            prefix: "lake/".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_from_s3() {
+        let list_query = "delimiter=%2F&list-type=2&prefix=lake%2F";
+        let responses = HashMap::from([
+            (
+                format!("/bucket?{}", list_query),
+                (
+                    200,
+                    b"<ListBucketResult><Prefix>lake/</Prefix>\
+                    <CommonPrefixes><Prefix>lake/block/</Prefix></CommonPrefixes>\
+                    <NextContinuationToken>next&amp;page</NextContinuationToken>\
+                    </ListBucketResult>"
+                        .to_vec(),
+                ),
+            ),
+            (
+                format!("/bucket?continuation-token=next%26page&{}", list_query),
+                (
+                    200,
+                    b"<ListBucketResult><Prefix>lake/</Prefix>\
+                    <CommonPrefixes><Prefix>lake/shard_0/</Prefix></CommonPrefixes>\
+                    </ListBucketResult>"
+                        .to_vec(),
+                ),
+            ),
+            (
+                "/bucket/lake/block/000000/000/000000000000.tgz".to_string(),
+                (
+                    200,
+                    tgz(&[
+                        ("000000000001.json", r#"{"block":1}"#),
+                        ("000000000003.json", r#"{"block":3}"#),
+                    ]),
+                ),
+            ),
+            (
+                "/bucket/lake/shard_0/000000/000/000000000000.tgz".to_string(),
+                (200, tgz(&[("000000000001.json", r#"{"shard":1}"#)])),
+            ),
+        ]);
+        let (endpoint, _) = start_s3_server(responses).await;
+        let mut reader = LakeReader::from_storage(s3_storage(endpoint), 1, 10)
+            .await
+            .unwrap();
+        assert_eq!(*reader.shards, vec!["shard_0"]);
+
+        let (group_height, group) = reader.next_group().await.unwrap().unwrap();
+        assert_eq!(group_height, 0);
+        let (block, shards) = group.block(1).unwrap();
+        assert_eq!(block, r#"{"block":1}"#);
+        assert_eq!(shards, vec![r#"{"shard":1}"#]);
+        // The shard of block 3 is missing.
+        assert_eq!(group.block(3).unwrap().1, Vec::<&String>::new());
+        assert!(group.block(2).is_none());
+
+        // The missing archives are empty groups.
+        let (group_height, group) = reader.next_group().await.unwrap().unwrap();
+        assert_eq!(group_height, 5);
+        assert!(group.block(5).is_none());
+        assert!(reader.next_group().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_s3_client_errors_are_not_retried() {
+        let responses = HashMap::from([(
+            "/bucket/lake/forbidden".to_string(),
+            (403, b"<Error><Code>AccessDenied</Code></Error>".to_vec()),
+        )]);
+        let (endpoint, num_requests) = start_s3_server(responses).await;
+        let storage = s3_storage(endpoint);
+        let err = storage.read("forbidden").await.unwrap_err();
+        assert!(err.to_string().contains("403"), "{}", err);
+        assert_eq!(num_requests.load(Ordering::SeqCst), 1);
+        assert!(storage.read("missing").await.unwrap().is_none());
+        assert_eq!(num_requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_read_local() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        std::fs::create_dir(dir.path().join("block")).unwrap();
+        std::fs::write(dir.path().join("block/file"), b"content").unwrap();
+        let storage = LakeStorage::from_path(path);
+        assert_eq!(storage.list_folders().await.unwrap(), vec!["block"]);
+        assert_eq!(
+            storage.read("block/file").await.unwrap(),
+            Some(b"content".to_vec())
+        );
+        assert_eq!(storage.read("block/missing").await.unwrap(), None);
+        // A truncated archive is an error instead of a group with missing blocks.
+        let archive = tgz(&[("000000000001.json", "{}"), ("000000000002.json", "{}")]);
+        std::fs::write(dir.path().join("block/archive.tgz"), &archive).unwrap();
+        assert_eq!(
+            storage
+                .read_archive("block/archive.tgz")
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+        std::fs::write(
+            dir.path().join("block/truncated.tgz"),
+            &archive[..archive.len() / 2],
+        )
+        .unwrap();
+        assert!(storage.read_archive("block/truncated.tgz").await.is_err());
+        // A missing lake is an error instead of a panic.
+        let missing = LakeStorage::from_path(&format!("{}/missing", path));
+        assert!(missing.list_folders().await.is_err());
+        assert!(
+            LakeReader::from_storage(LakeStorage::from_path(&format!("{}/block", path)), 0, 5)
+                .await
+                .is_err()
+        );
+    }
+}
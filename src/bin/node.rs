@@ -1,5 +1,6 @@
 mod click;
 mod common;
+mod extract;
 
 use click::*;
 use dotenv::dotenv;
@@ -13,18 +14,24 @@ fn main() {
     let args: Vec<String> = std::env::args().collect();
     let home_dir = std::path::PathBuf::from(near_indexer::get_default_home());
 
-    common::setup_tracing("clickhouse=info,tokio_reactor=info,near=info,stats=info,telemetry=info,indexer=info,aggregated=info,compact_indexer=info");
+    common::setup_tracing("clickhouse=info,tokio_reactor=info,near=info,stats=info,telemetry=info,indexer=info,aggregated=info,compact_indexer=info,metrics=info");
 
     tracing::log::info!(target: PROJECT_ID, "Starting indexer");
 
     let command = args
         .get(1)
         .map(|arg| arg.as_str())
-        .expect("You need to provide a command: `init` or `run` as arg");
+        .expect("You need to provide a command: `run` or `migrate` as arg");
 
     match command {
+        "migrate" => {
+            let db = ClickDB::new(PROJECT_ID, PROJECT_ID, 1);
+            actix::System::new()
+                .block_on(db.migrate())
+                .expect("Failed to migrate the ClickHouse schema");
+        }
         "run" => {
-            let db = ClickDB::new(1);
+            let db = ClickDB::new(PROJECT_ID, PROJECT_ID, 1);
             let indexer_config = near_indexer::IndexerConfig {
                 home_dir,
                 sync_mode: near_indexer::SyncModeEnum::FromInterruption,
@@ -41,7 +48,7 @@ fn main() {
             });
             sys.run().unwrap();
         }
-        _ => panic!("You have to pass `run` arg"),
+        _ => panic!("You have to pass `run` or `migrate` arg"),
     }
 }
 
@@ -49,8 +56,30 @@ async fn listen_blocks(
     mut stream: tokio::sync::mpsc::Receiver<near_indexer::StreamerMessage>,
     mut db: ClickDB,
 ) {
-    while let Some(streamer_message) = stream.recv().await {
-        extract_info(&mut db, streamer_message).await.unwrap();
+    db.validate_schema()
+        .await
+        .expect("Invalid ClickHouse schema");
+    start_metrics_server();
+    if let Some(last_block_height) = db
+        .load_checkpoint()
+        .await
+        .expect("Failed to get the checkpoint from clickhouse")
+    {
+        db.resume_after(last_block_height);
+    }
+    let mut interval = tokio::time::interval(FLUSH_CHECK_INTERVAL);
+    loop {
+        tokio::select! {
+            streamer_message = stream.recv() => {
+                let Some(streamer_message) = streamer_message else {
+                    break;
+                };
+                extract_info(&mut db, streamer_message.into()).await.unwrap();
+            }
+            _ = interval.tick() => {
+                db.flush_if_stale().await.unwrap();
+            }
+        }
     }
     db.commit().await.unwrap();
 }
@@ -1,96 +1,105 @@
+mod block_source;
 mod click;
 mod common;
+mod extract;
+mod lake_reader;
 mod redis_db;
+mod xblock;
 
+use block_source::BlockSource;
 use click::ClickDB;
-use redis_db::RedisDB;
 
-use crate::click::extract_info;
+use crate::click::{
+    extract_info, migrate_if_requested, start_metrics_server, FLUSH_CHECK_INTERVAL,
+};
 use dotenv::dotenv;
-use near_indexer::near_primitives::types::BlockHeight;
-use near_indexer::StreamerMessage;
+use fastnear_primitives::block_with_tx_hash::BlockWithTxHashes;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use tokio::sync::mpsc;
 
 const PROJECT_ID: &str = "redis_indexer";
 
-const FINAL_BLOCKS_KEY: &str = "final_blocks";
-const BLOCK_KEY: &str = "block";
-
-async fn start(
-    mut last_id: String,
-    mut redis_db: RedisDB,
-    blocks_sink: mpsc::Sender<StreamerMessage>,
-) {
-    loop {
-        let res = redis_db.xread(1, FINAL_BLOCKS_KEY, &last_id).await;
-        let res = match res {
-            Ok(res) => res,
-            Err(err) => {
-                tracing::log::error!(target: PROJECT_ID, "Error: {}", err);
-                tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
-                let _ = redis_db.reconnect().await;
-                continue;
-            }
-        };
-        let (id, key_values) = res.into_iter().next().unwrap();
-        assert_eq!(key_values.len(), 1, "Expected 1 key-value pair");
-        let (key, value) = key_values.into_iter().next().unwrap();
-        assert_eq!(key, BLOCK_KEY, "Expected key to be block");
-        let streamer_message: StreamerMessage = serde_json::from_str(&value).unwrap();
-        blocks_sink.send(streamer_message).await.unwrap();
-        last_id = id;
-    }
-}
-
-pub fn streamer(last_id: String, redis_db: RedisDB) -> mpsc::Receiver<StreamerMessage> {
-    let (sender, receiver) = mpsc::channel(100);
-    tokio::spawn(start(last_id, redis_db, sender));
-    receiver
-}
-
 #[tokio::main]
 async fn main() {
     openssl_probe::init_ssl_cert_env_vars();
     dotenv().ok();
 
-    common::setup_tracing("clickhouse=info,redis_indexer=info");
+    common::setup_tracing(
+        "clickhouse=info,redis_indexer=info,block_source=info,neardata-fetcher=info,metrics=info",
+    );
 
     tracing::log::info!(target: PROJECT_ID, "Starting NEAR Redis Indexer");
 
-    let click_db = ClickDB::new(1);
-    let mut redis_db = RedisDB::new().await;
+    let is_running = Arc::new(AtomicBool::new(true));
+    let ctrl_c_running = is_running.clone();
+    ctrlc::set_handler(move || {
+        ctrl_c_running.store(false, Ordering::SeqCst);
+        println!("Received Ctrl+C, starting shutdown...");
+    })
+    .expect("Error setting Ctrl+C handler");
+
+    let mut click_db = ClickDB::new(PROJECT_ID, PROJECT_ID, 1);
 
-    let (id, _key_values) = redis_db
-        .xread(1, FINAL_BLOCKS_KEY, "0")
+    if migrate_if_requested(&click_db).await {
+        return;
+    }
+    click_db
+        .validate_schema()
         .await
-        .expect("Failed to get the first block from Redis")
-        .into_iter()
-        .next()
-        .unwrap();
-    let first_block_height: BlockHeight = id.split_once("-").unwrap().0.parse().unwrap();
-    tracing::log::info!(target: PROJECT_ID, "First redis block {}", first_block_height);
+        .expect("Invalid ClickHouse schema");
+    start_metrics_server();
+
+    let block_source = BlockSource::from_env("redis");
 
     let last_block_height = click_db
-        .last_block_height()
+        .load_checkpoint()
         .await
-        .expect("Failed to get last block from clickhouse");
-    let last_id = last_block_height
-        .map(|h| format!("{}-0", h))
-        .unwrap_or("0".to_string());
+        .expect("Failed to get the checkpoint from clickhouse");
     tracing::log::info!(target: PROJECT_ID, "Resuming from {}", last_block_height.unwrap_or(0));
+    if let Some(last_block_height) = last_block_height {
+        click_db.resume_after(last_block_height);
+    }
+
+    let mut start_block_height = last_block_height.map(|h| h + 1).unwrap_or(0);
+    if let BlockSource::Redis(redis_config) = &block_source {
+        let first_block_height = redis_config
+            .first_block_height()
+            .await
+            .expect("Failed to get the first block from Redis")
+            .expect("The redis stream is empty");
+        tracing::log::info!(target: PROJECT_ID, "First redis block {}", first_block_height);
 
-    if first_block_height + 30 > last_block_height.unwrap_or(0) {
-        panic!("The first block in the redis is too close to the last block in the clickhouse");
+        match last_block_height {
+            Some(last_block_height) => {
+                if first_block_height + 30 > last_block_height {
+                    panic!("The first block in the redis is too close to the last block in the clickhouse");
+                }
+            }
+            // Without a checkpoint, the stream is indexed from its first block.
+            None => start_block_height = first_block_height,
+        }
     }
 
-    let stream = streamer(last_id, redis_db);
+    let stream = block_source.streamer(start_block_height, is_running);
     listen_blocks(stream, click_db).await;
 }
 
-async fn listen_blocks(mut stream: mpsc::Receiver<StreamerMessage>, mut db: ClickDB) {
-    while let Some(streamer_message) = stream.recv().await {
-        tracing::log::info!(target: PROJECT_ID, "Processing block: {}", streamer_message.block.header.height);
-        extract_info(&mut db, streamer_message).await.unwrap();
+async fn listen_blocks(mut stream: mpsc::Receiver<BlockWithTxHashes>, mut db: ClickDB) {
+    let mut interval = tokio::time::interval(FLUSH_CHECK_INTERVAL);
+    loop {
+        tokio::select! {
+            block = stream.recv() => {
+                let Some(block) = block else {
+                    break;
+                };
+                tracing::log::info!(target: PROJECT_ID, "Processing block: {}", block.block.header.height);
+                extract_info(&mut db, block).await.unwrap();
+            }
+            _ = interval.tick() => {
+                db.flush_if_stale().await.unwrap();
+            }
+        }
     }
     db.commit().await.unwrap();
 }
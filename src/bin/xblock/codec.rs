@@ -0,0 +1,171 @@
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::io::{BufReader, Read, Write};
+
+/// The zstd dictionary used for the archives of a folder is stored in the folder under this name,
+/// so the readers don't need to be configured with it.
+pub const ZSTD_DICTIONARY_FILE: &str = "zstd.dict";
+#[allow(dead_code)]
+const DEFAULT_ZSTD_LEVEL: i32 = 3;
+
+/// The compression of a tar archive of blocks. The codec is recorded in the file extension.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveCodec {
+    Zstd,
+    Gzip,
+    None,
+}
+
+impl ArchiveCodec {
+    /// All codecs, in the order the readers look for the archives.
+    pub const ALL: [ArchiveCodec; 3] = [ArchiveCodec::Zstd, ArchiveCodec::Gzip, ArchiveCodec::None];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ArchiveCodec::Zstd => "tar.zst",
+            ArchiveCodec::Gzip => "tgz",
+            ArchiveCodec::None => "tar",
+        }
+    }
+
+    /// Returns a reader of the decompressed tar. The dictionary is only used by zstd.
+    pub fn decoder<'a, R: Read + 'a>(
+        &self,
+        reader: R,
+        zstd_dictionary: Option<&'a [u8]>,
+    ) -> std::io::Result<Box<dyn Read + 'a>> {
+        Ok(match self {
+            ArchiveCodec::Zstd => Box::new(zstd::stream::read::Decoder::with_dictionary(
+                BufReader::new(reader),
+                zstd_dictionary.unwrap_or_default(),
+            )?),
+            ArchiveCodec::Gzip => Box::new(GzDecoder::new(reader)),
+            ArchiveCodec::None => Box::new(reader),
+        })
+    }
+}
+
+/// How `lake-convert` compresses the archives.
+#[allow(dead_code)]
+pub struct EncoderConfig {
+    pub codec: ArchiveCodec,
+    pub zstd_level: i32,
+    /// A dictionary trained on the borsh blocks, e.g. with `zstd --train`.
+    pub zstd_dictionary: Option<Vec<u8>>,
+}
+
+#[allow(dead_code)]
+impl EncoderConfig {
+    pub fn from_env() -> Self {
+        let codec = match env::var("ARCHIVE_CODEC")
+            .unwrap_or("gzip".to_string())
+            .as_str()
+        {
+            "zstd" => ArchiveCodec::Zstd,
+            "gzip" => ArchiveCodec::Gzip,
+            "none" => ArchiveCodec::None,
+            codec => panic!(
+                "Unknown ARCHIVE_CODEC `{}`. Expected `zstd`, `gzip` or `none`",
+                codec
+            ),
+        };
+        Self {
+            codec,
+            zstd_level: env::var("ARCHIVE_ZSTD_LEVEL")
+                .map(|s| s.parse().expect("Invalid ARCHIVE_ZSTD_LEVEL"))
+                .unwrap_or(DEFAULT_ZSTD_LEVEL),
+            zstd_dictionary: env::var("ARCHIVE_ZSTD_DICTIONARY")
+                .ok()
+                .map(|path| std::fs::read(path).expect("Failed to read ARCHIVE_ZSTD_DICTIONARY")),
+        }
+    }
+
+    /// Returns a writer that compresses the tar archive into the given writer.
+    pub fn encoder<W: Write>(&self, writer: W) -> std::io::Result<ArchiveEncoder<W>> {
+        Ok(match self.codec {
+            ArchiveCodec::Zstd => {
+                let mut encoder = zstd::stream::write::Encoder::with_dictionary(
+                    writer,
+                    self.zstd_level,
+                    self.zstd_dictionary.as_deref().unwrap_or_default(),
+                )?;
+                encoder.include_checksum(true)?;
+                ArchiveEncoder::Zstd(encoder)
+            }
+            ArchiveCodec::Gzip => {
+                ArchiveEncoder::Gzip(GzEncoder::new(writer, flate2::Compression::default()))
+            }
+            ArchiveCodec::None => ArchiveEncoder::None(writer),
+        })
+    }
+
+    /// Stores the zstd dictionary in the archives folder. Fails if the folder already has a
+    /// different dictionary, because the existing archives can only be read with it.
+    pub fn save_dictionary(&self, path: &str) -> std::io::Result<()> {
+        let Some(dictionary) = self
+            .zstd_dictionary
+            .as_ref()
+            .filter(|_| self.codec == ArchiveCodec::Zstd)
+        else {
+            return Ok(());
+        };
+        std::fs::create_dir_all(path)?;
+        let dictionary_path = format!("{}/{}", path, ZSTD_DICTIONARY_FILE);
+        match std::fs::read(&dictionary_path) {
+            Ok(existing) if &existing == dictionary => Ok(()),
+            Ok(_) => Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!(
+                    "A different zstd dictionary is already in {}",
+                    dictionary_path
+                ),
+            )),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                std::fs::write(&dictionary_path, dictionary)
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// A writer compressing with one of the codecs. `finish` has to be called to write the end of the
+/// compressed stream.
+#[allow(dead_code)]
+pub enum ArchiveEncoder<W: Write> {
+    Zstd(zstd::stream::write::Encoder<'static, W>),
+    Gzip(GzEncoder<W>),
+    None(W),
+}
+
+#[allow(dead_code)]
+impl<W: Write> ArchiveEncoder<W> {
+    /// Finishes the compressed stream and returns the inner writer.
+    pub fn finish(self) -> std::io::Result<W> {
+        match self {
+            ArchiveEncoder::Zstd(encoder) => encoder.finish(),
+            ArchiveEncoder::Gzip(encoder) => encoder.finish(),
+            ArchiveEncoder::None(writer) => Ok(writer),
+        }
+    }
+}
+
+impl<W: Write> Write for ArchiveEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            ArchiveEncoder::Zstd(encoder) => encoder.write(buf),
+            ArchiveEncoder::Gzip(encoder) => encoder.write(buf),
+            ArchiveEncoder::None(writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            ArchiveEncoder::Zstd(encoder) => encoder.flush(),
+            ArchiveEncoder::Gzip(encoder) => encoder.flush(),
+            ArchiveEncoder::None(writer) => writer.flush(),
+        }
+    }
+}
@@ -0,0 +1,597 @@
+use crate::xblock::*;
+use fastnear_primitives::block_with_tx_hash::{BlockWithTxHashes, IndexerShardWithTxHashes};
+use fastnear_primitives::near_indexer_primitives::{
+    IndexerChunkView, IndexerExecutionOutcomeWithOptionalReceipt,
+    IndexerExecutionOutcomeWithReceipt, IndexerShard, IndexerTransactionWithOutcome,
+    StreamerMessage,
+};
+use fastnear_primitives::near_primitives::views::{
+    AccountView, BlockHeaderView, BlockView, ChunkHeaderView, StateChangeCauseView,
+    StateChangeValueView, StateChangeWithCauseView,
+};
+
+impl From<XBlock> for StreamerMessage {
+    fn from(xblock: XBlock) -> Self {
+        Self {
+            block: xblock.block.into(),
+            shards: xblock.shards.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// The archives don't store the transaction hashes of the receipts, so `tx_hash` is always None,
+/// the same as for the blocks from the lake.
+impl From<XBlock> for BlockWithTxHashes {
+    fn from(xblock: XBlock) -> Self {
+        StreamerMessage::from(xblock).into()
+    }
+}
+
+impl From<XBlockView> for BlockView {
+    fn from(block: XBlockView) -> Self {
+        Self {
+            author: block.author,
+            header: block.header.into(),
+            chunks: block.chunks.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<XBlockHeaderView> for BlockHeaderView {
+    fn from(header: XBlockHeaderView) -> Self {
+        Self {
+            height: header.height,
+            prev_height: header.prev_height,
+            epoch_id: header.epoch_id,
+            next_epoch_id: header.next_epoch_id,
+            hash: header.hash,
+            prev_hash: header.prev_hash,
+            prev_state_root: header.prev_state_root,
+            block_body_hash: header.block_body_hash,
+            chunk_receipts_root: header.chunk_receipts_root,
+            chunk_headers_root: header.chunk_headers_root,
+            chunk_tx_root: header.chunk_tx_root,
+            outcome_root: header.outcome_root,
+            chunks_included: header.chunks_included,
+            challenges_root: header.challenges_root,
+            timestamp: header.timestamp,
+            timestamp_nanosec: header.timestamp_nanosec,
+            random_value: header.random_value,
+            validator_proposals: header.validator_proposals,
+            chunk_mask: header.chunk_mask,
+            gas_price: header.gas_price,
+            block_ordinal: header.block_ordinal,
+            rent_paid: header.rent_paid,
+            validator_reward: header.validator_reward,
+            total_supply: header.total_supply,
+            challenges_result: header.challenges_result,
+            last_final_block: header.last_final_block,
+            last_ds_final_block: header.last_ds_final_block,
+            next_bp_hash: header.next_bp_hash,
+            block_merkle_root: header.block_merkle_root,
+            epoch_sync_data_hash: header.epoch_sync_data_hash,
+            approvals: header
+                .approvals
+                .into_iter()
+                .map(|approval| approval.map(Box::new))
+                .collect(),
+            signature: header.signature,
+            latest_protocol_version: header.latest_protocol_version,
+        }
+    }
+}
+
+impl From<XChunkHeaderView> for ChunkHeaderView {
+    fn from(header: XChunkHeaderView) -> Self {
+        Self {
+            chunk_hash: header.chunk_hash,
+            prev_block_hash: header.prev_block_hash,
+            outcome_root: header.outcome_root,
+            prev_state_root: header.prev_state_root,
+            encoded_merkle_root: header.encoded_merkle_root,
+            encoded_length: header.encoded_length,
+            height_created: header.height_created,
+            height_included: header.height_included,
+            shard_id: header.shard_id,
+            gas_used: header.gas_used,
+            gas_limit: header.gas_limit,
+            rent_paid: header.rent_paid,
+            validator_reward: header.validator_reward,
+            balance_burnt: header.balance_burnt,
+            outgoing_receipts_root: header.outgoing_receipts_root,
+            tx_root: header.tx_root,
+            validator_proposals: header.validator_proposals,
+            signature: header.signature,
+        }
+    }
+}
+
+impl From<XIndexerShard> for IndexerShard {
+    fn from(shard: XIndexerShard) -> Self {
+        Self {
+            shard_id: shard.shard_id,
+            chunk: shard.chunk.map(Into::into),
+            receipt_execution_outcomes: shard
+                .receipt_execution_outcomes
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            state_changes: shard.state_changes.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<XIndexerChunkView> for IndexerChunkView {
+    fn from(chunk: XIndexerChunkView) -> Self {
+        Self {
+            author: chunk.author,
+            header: chunk.header.into(),
+            transactions: chunk.transactions.into_iter().map(Into::into).collect(),
+            receipts: chunk.receipts,
+        }
+    }
+}
+
+impl From<XIndexerTransactionWithOutcome> for IndexerTransactionWithOutcome {
+    fn from(tx: XIndexerTransactionWithOutcome) -> Self {
+        Self {
+            transaction: tx.transaction,
+            outcome: IndexerExecutionOutcomeWithOptionalReceipt {
+                execution_outcome: tx.outcome.execution_outcome,
+                receipt: tx.outcome.receipt,
+            },
+        }
+    }
+}
+
+impl From<XIndexerExecutionOutcomeWithReceipt> for IndexerExecutionOutcomeWithReceipt {
+    fn from(outcome: XIndexerExecutionOutcomeWithReceipt) -> Self {
+        Self {
+            execution_outcome: outcome.execution_outcome,
+            receipt: outcome.receipt,
+        }
+    }
+}
+
+impl From<XStateChangeWithCauseView> for StateChangeWithCauseView {
+    fn from(state_change: XStateChangeWithCauseView) -> Self {
+        Self {
+            cause: state_change.cause.into(),
+            value: state_change.value.into(),
+        }
+    }
+}
+
+impl From<XStateChangeCauseView> for StateChangeCauseView {
+    fn from(cause: XStateChangeCauseView) -> Self {
+        match cause {
+            XStateChangeCauseView::NotWritableToDisk => Self::NotWritableToDisk,
+            XStateChangeCauseView::InitialState => Self::InitialState,
+            XStateChangeCauseView::TransactionProcessing { tx_hash } => {
+                Self::TransactionProcessing { tx_hash }
+            }
+            XStateChangeCauseView::ActionReceiptProcessingStarted { receipt_hash } => {
+                Self::ActionReceiptProcessingStarted { receipt_hash }
+            }
+            XStateChangeCauseView::ActionReceiptGasReward { receipt_hash } => {
+                Self::ActionReceiptGasReward { receipt_hash }
+            }
+            XStateChangeCauseView::ReceiptProcessing { receipt_hash } => {
+                Self::ReceiptProcessing { receipt_hash }
+            }
+            XStateChangeCauseView::PostponedReceipt { receipt_hash } => {
+                Self::PostponedReceipt { receipt_hash }
+            }
+            XStateChangeCauseView::UpdatedDelayedReceipts => Self::UpdatedDelayedReceipts,
+            XStateChangeCauseView::ValidatorAccountsUpdate => Self::ValidatorAccountsUpdate,
+            XStateChangeCauseView::Migration => Self::Migration,
+            XStateChangeCauseView::Resharding => Self::Resharding,
+        }
+    }
+}
+
+impl From<XStateChangeValueView> for StateChangeValueView {
+    fn from(value: XStateChangeValueView) -> Self {
+        match value {
+            XStateChangeValueView::AccountUpdate {
+                account_id,
+                account,
+            } => Self::AccountUpdate {
+                account_id,
+                account: account.into(),
+            },
+            XStateChangeValueView::AccountDeletion { account_id } => {
+                Self::AccountDeletion { account_id }
+            }
+            XStateChangeValueView::AccessKeyUpdate {
+                account_id,
+                public_key,
+                access_key,
+            } => Self::AccessKeyUpdate {
+                account_id,
+                public_key,
+                access_key,
+            },
+            XStateChangeValueView::AccessKeyDeletion {
+                account_id,
+                public_key,
+            } => Self::AccessKeyDeletion {
+                account_id,
+                public_key,
+            },
+            XStateChangeValueView::DataUpdate {
+                account_id,
+                key,
+                value,
+            } => Self::DataUpdate {
+                account_id,
+                key,
+                value,
+            },
+            XStateChangeValueView::DataDeletion { account_id, key } => {
+                Self::DataDeletion { account_id, key }
+            }
+            XStateChangeValueView::ContractCodeUpdate { account_id, code } => {
+                Self::ContractCodeUpdate { account_id, code }
+            }
+            XStateChangeValueView::ContractCodeDeletion { account_id } => {
+                Self::ContractCodeDeletion { account_id }
+            }
+        }
+    }
+}
+
+impl From<XAccountView> for AccountView {
+    fn from(account: XAccountView) -> Self {
+        Self {
+            amount: account.amount,
+            locked: account.locked,
+            code_hash: account.code_hash,
+            storage_usage: account.storage_usage,
+            storage_paid_at: account.storage_paid_at,
+        }
+    }
+}
+
+impl From<StreamerMessage> for XBlock {
+    fn from(streamer_message: StreamerMessage) -> Self {
+        Self {
+            block: streamer_message.block.into(),
+            shards: streamer_message
+                .shards
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+        }
+    }
+}
+
+/// The transaction hashes of the receipts are dropped.
+impl From<BlockWithTxHashes> for XBlock {
+    fn from(block: BlockWithTxHashes) -> Self {
+        Self {
+            block: block.block.into(),
+            shards: block.shards.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<IndexerShardWithTxHashes> for XIndexerShard {
+    fn from(shard: IndexerShardWithTxHashes) -> Self {
+        Self {
+            shard_id: shard.shard_id,
+            chunk: shard.chunk.map(Into::into),
+            receipt_execution_outcomes: shard
+                .receipt_execution_outcomes
+                .into_iter()
+                .map(|outcome| XIndexerExecutionOutcomeWithReceipt {
+                    execution_outcome: outcome.execution_outcome,
+                    receipt: outcome.receipt,
+                })
+                .collect(),
+            state_changes: shard.state_changes.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<BlockView> for XBlockView {
+    fn from(block: BlockView) -> Self {
+        Self {
+            author: block.author,
+            header: block.header.into(),
+            chunks: block.chunks.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<BlockHeaderView> for XBlockHeaderView {
+    fn from(header: BlockHeaderView) -> Self {
+        Self {
+            height: header.height,
+            prev_height: header.prev_height,
+            epoch_id: header.epoch_id,
+            next_epoch_id: header.next_epoch_id,
+            hash: header.hash,
+            prev_hash: header.prev_hash,
+            prev_state_root: header.prev_state_root,
+            block_body_hash: header.block_body_hash,
+            chunk_receipts_root: header.chunk_receipts_root,
+            chunk_headers_root: header.chunk_headers_root,
+            chunk_tx_root: header.chunk_tx_root,
+            outcome_root: header.outcome_root,
+            chunks_included: header.chunks_included,
+            challenges_root: header.challenges_root,
+            timestamp: header.timestamp,
+            timestamp_nanosec: header.timestamp_nanosec,
+            random_value: header.random_value,
+            validator_proposals: header.validator_proposals,
+            chunk_mask: header.chunk_mask,
+            gas_price: header.gas_price,
+            block_ordinal: header.block_ordinal,
+            rent_paid: header.rent_paid,
+            validator_reward: header.validator_reward,
+            total_supply: header.total_supply,
+            challenges_result: header.challenges_result,
+            last_final_block: header.last_final_block,
+            last_ds_final_block: header.last_ds_final_block,
+            next_bp_hash: header.next_bp_hash,
+            block_merkle_root: header.block_merkle_root,
+            epoch_sync_data_hash: header.epoch_sync_data_hash,
+            approvals: header
+                .approvals
+                .into_iter()
+                .map(|approval| approval.map(|signature| *signature))
+                .collect(),
+            signature: header.signature,
+            latest_protocol_version: header.latest_protocol_version,
+        }
+    }
+}
+
+impl From<ChunkHeaderView> for XChunkHeaderView {
+    fn from(header: ChunkHeaderView) -> Self {
+        Self {
+            chunk_hash: header.chunk_hash,
+            prev_block_hash: header.prev_block_hash,
+            outcome_root: header.outcome_root,
+            prev_state_root: header.prev_state_root,
+            encoded_merkle_root: header.encoded_merkle_root,
+            encoded_length: header.encoded_length,
+            height_created: header.height_created,
+            height_included: header.height_included,
+            shard_id: header.shard_id,
+            gas_used: header.gas_used,
+            gas_limit: header.gas_limit,
+            rent_paid: header.rent_paid,
+            validator_reward: header.validator_reward,
+            balance_burnt: header.balance_burnt,
+            outgoing_receipts_root: header.outgoing_receipts_root,
+            tx_root: header.tx_root,
+            validator_proposals: header.validator_proposals,
+            signature: header.signature,
+        }
+    }
+}
+
+impl From<IndexerShard> for XIndexerShard {
+    fn from(shard: IndexerShard) -> Self {
+        Self {
+            shard_id: shard.shard_id,
+            chunk: shard.chunk.map(Into::into),
+            receipt_execution_outcomes: shard
+                .receipt_execution_outcomes
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            state_changes: shard.state_changes.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<IndexerChunkView> for XIndexerChunkView {
+    fn from(chunk: IndexerChunkView) -> Self {
+        Self {
+            author: chunk.author,
+            header: chunk.header.into(),
+            transactions: chunk.transactions.into_iter().map(Into::into).collect(),
+            receipts: chunk.receipts,
+        }
+    }
+}
+
+impl From<IndexerTransactionWithOutcome> for XIndexerTransactionWithOutcome {
+    fn from(tx: IndexerTransactionWithOutcome) -> Self {
+        Self {
+            transaction: tx.transaction,
+            outcome: XIndexerExecutionOutcomeWithOptionalReceipt {
+                execution_outcome: tx.outcome.execution_outcome,
+                receipt: tx.outcome.receipt,
+            },
+        }
+    }
+}
+
+impl From<IndexerExecutionOutcomeWithReceipt> for XIndexerExecutionOutcomeWithReceipt {
+    fn from(outcome: IndexerExecutionOutcomeWithReceipt) -> Self {
+        Self {
+            execution_outcome: outcome.execution_outcome,
+            receipt: outcome.receipt,
+        }
+    }
+}
+
+impl From<StateChangeWithCauseView> for XStateChangeWithCauseView {
+    fn from(state_change: StateChangeWithCauseView) -> Self {
+        Self {
+            cause: state_change.cause.into(),
+            value: state_change.value.into(),
+        }
+    }
+}
+
+impl From<StateChangeCauseView> for XStateChangeCauseView {
+    fn from(cause: StateChangeCauseView) -> Self {
+        match cause {
+            StateChangeCauseView::NotWritableToDisk => Self::NotWritableToDisk,
+            StateChangeCauseView::InitialState => Self::InitialState,
+            StateChangeCauseView::TransactionProcessing { tx_hash } => {
+                Self::TransactionProcessing { tx_hash }
+            }
+            StateChangeCauseView::ActionReceiptProcessingStarted { receipt_hash } => {
+                Self::ActionReceiptProcessingStarted { receipt_hash }
+            }
+            StateChangeCauseView::ActionReceiptGasReward { receipt_hash } => {
+                Self::ActionReceiptGasReward { receipt_hash }
+            }
+            StateChangeCauseView::ReceiptProcessing { receipt_hash } => {
+                Self::ReceiptProcessing { receipt_hash }
+            }
+            StateChangeCauseView::PostponedReceipt { receipt_hash } => {
+                Self::PostponedReceipt { receipt_hash }
+            }
+            StateChangeCauseView::UpdatedDelayedReceipts => Self::UpdatedDelayedReceipts,
+            StateChangeCauseView::ValidatorAccountsUpdate => Self::ValidatorAccountsUpdate,
+            StateChangeCauseView::Migration => Self::Migration,
+            StateChangeCauseView::Resharding => Self::Resharding,
+        }
+    }
+}
+
+impl From<StateChangeValueView> for XStateChangeValueView {
+    fn from(value: StateChangeValueView) -> Self {
+        match value {
+            StateChangeValueView::AccountUpdate {
+                account_id,
+                account,
+            } => Self::AccountUpdate {
+                account_id,
+                account: account.into(),
+            },
+            StateChangeValueView::AccountDeletion { account_id } => {
+                Self::AccountDeletion { account_id }
+            }
+            StateChangeValueView::AccessKeyUpdate {
+                account_id,
+                public_key,
+                access_key,
+            } => Self::AccessKeyUpdate {
+                account_id,
+                public_key,
+                access_key,
+            },
+            StateChangeValueView::AccessKeyDeletion {
+                account_id,
+                public_key,
+            } => Self::AccessKeyDeletion {
+                account_id,
+                public_key,
+            },
+            StateChangeValueView::DataUpdate {
+                account_id,
+                key,
+                value,
+            } => Self::DataUpdate {
+                account_id,
+                key,
+                value,
+            },
+            StateChangeValueView::DataDeletion { account_id, key } => {
+                Self::DataDeletion { account_id, key }
+            }
+            StateChangeValueView::ContractCodeUpdate { account_id, code } => {
+                Self::ContractCodeUpdate { account_id, code }
+            }
+            StateChangeValueView::ContractCodeDeletion { account_id } => {
+                Self::ContractCodeDeletion { account_id }
+            }
+        }
+    }
+}
+
+impl From<AccountView> for XAccountView {
+    fn from(account: AccountView) -> Self {
+        Self {
+            amount: account.amount,
+            locked: account.locked,
+            code_hash: account.code_hash,
+            storage_usage: account.storage_usage,
+            storage_paid_at: account.storage_paid_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use borsh::BorshDeserialize;
+
+    const FIXTURES: &[&str] = &[
+        include_str!("../../../tests/fixtures/streamer_message.json"),
+        include_str!("../../../tests/fixtures/streamer_message_legacy.json"),
+    ];
+
+    fn borsh_round_trip(xblock: &XBlock) -> XBlock {
+        XBlock::try_from_slice(&borsh::to_vec(xblock).unwrap()).unwrap()
+    }
+
+    /// JSON -> XBlock -> borsh -> XBlock -> JSON has to be lossless.
+    #[test]
+    fn test_streamer_message_round_trip() {
+        for fixture in FIXTURES {
+            let json: serde_json::Value = serde_json::from_str(fixture).unwrap();
+            let streamer_message: StreamerMessage = serde_json::from_value(json.clone()).unwrap();
+            let xblock = borsh_round_trip(&streamer_message.into());
+            assert_eq!(
+                serde_json::to_value(StreamerMessage::from(xblock)).unwrap(),
+                json
+            );
+        }
+    }
+
+    /// `lake-convert` parses the lake JSON directly into `XBlock`, so it has to match the upstream
+    /// views in both JSON and borsh.
+    #[test]
+    fn test_xblock_json_matches_upstream() {
+        for fixture in FIXTURES {
+            let json: serde_json::Value = serde_json::from_str(fixture).unwrap();
+            let xblock: XBlock = serde_json::from_value(json.clone()).unwrap();
+            let xblock = borsh_round_trip(&xblock);
+            assert_eq!(serde_json::to_value(&xblock).unwrap(), json);
+
+            let streamer_message: StreamerMessage = serde_json::from_value(json).unwrap();
+            assert_eq!(
+                borsh::to_vec(&xblock).unwrap(),
+                borsh::to_vec(&XBlock::from(streamer_message)).unwrap()
+            );
+        }
+    }
+
+    /// The transaction hashes of the receipts aren't stored, so they come back as None. Everything
+    /// else is lossless.
+    #[test]
+    fn test_block_with_tx_hashes_round_trip() {
+        for fixture in FIXTURES {
+            let streamer_message: StreamerMessage = serde_json::from_str(fixture).unwrap();
+            let block = BlockWithTxHashes::from(streamer_message);
+            let json = serde_json::to_value(&block).unwrap();
+            let mut block_with_tx_hashes: BlockWithTxHashes =
+                serde_json::from_value(json.clone()).unwrap();
+            let mut num_tx_hashes = 0;
+            for shard in &mut block_with_tx_hashes.shards {
+                for outcome in &mut shard.receipt_execution_outcomes {
+                    outcome.tx_hash = Some(outcome.receipt.receipt_id);
+                    num_tx_hashes += 1;
+                }
+            }
+            assert!(num_tx_hashes > 0);
+            let xblock = borsh_round_trip(&block_with_tx_hashes.into());
+            let block = BlockWithTxHashes::from(xblock);
+            assert!(block
+                .shards
+                .iter()
+                .flat_map(|shard| &shard.receipt_execution_outcomes)
+                .all(|outcome| outcome.tx_hash.is_none()));
+            assert_eq!(serde_json::to_value(block).unwrap(), json);
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use crate::xblock::codec::ArchiveCodec;
+use crate::xblock::reader::archive_path;
+use crate::xblock::XBlock;
+use fastnear_primitives::near_primitives::hash::CryptoHash;
+use fastnear_primitives::near_primitives::types::BlockHeight;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// The index of an archive, stored next to it as `<height>.manifest.json`. It's written after the
+/// archive, so an archive without a manifest is incomplete. Both are written atomically.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ArchiveManifest {
+    pub archive_height: BlockHeight,
+    pub codec: ArchiveCodec,
+    /// Hex SHA-256 of the archive file.
+    pub sha256: String,
+    pub size: u64,
+    /// The blocks in the archive, ordered by the block height.
+    pub blocks: Vec<ManifestBlock>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ManifestBlock {
+    pub height: BlockHeight,
+    pub hash: CryptoHash,
+    pub prev_hash: CryptoHash,
+}
+
+impl From<&XBlock> for ManifestBlock {
+    fn from(block: &XBlock) -> Self {
+        Self {
+            height: block.block.header.height,
+            hash: block.block.header.hash,
+            prev_hash: block.block.header.prev_hash,
+        }
+    }
+}
+
+/// Returns the path of the manifest of the archive starting at the given block height:
+/// `<path>/<height / 10^6>/<height>.manifest.json`.
+#[allow(dead_code)]
+pub fn manifest_path(path: &str, archive_height: BlockHeight) -> String {
+    let padded_height = format!("{:0>12}", archive_height);
+    format!(
+        "{}/{}/{}.manifest.json",
+        path,
+        &padded_height[..6],
+        padded_height
+    )
+}
+
+#[allow(dead_code)]
+pub fn sha256_hex(content: &[u8]) -> String {
+    hex::encode(ring::digest::digest(&ring::digest::SHA256, content))
+}
+
+#[allow(dead_code)]
+impl ArchiveManifest {
+    /// Creates the manifest of an archive file with the given hex SHA-256 and size.
+    pub fn new(
+        archive_height: BlockHeight,
+        codec: ArchiveCodec,
+        sha256: String,
+        size: u64,
+        blocks: &[XBlock],
+    ) -> Self {
+        Self {
+            archive_height,
+            codec,
+            sha256,
+            size,
+            blocks: blocks.iter().map(Into::into).collect(),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        write_atomically(
+            &manifest_path(path, self.archive_height),
+            &serde_json::to_vec_pretty(self)?,
+        )
+    }
+
+    /// Returns None if the manifest doesn't exist.
+    pub fn load(path: &str, archive_height: BlockHeight) -> std::io::Result<Option<Self>> {
+        let manifest_path = manifest_path(path, archive_height);
+        if !Path::new(&manifest_path).exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_slice(&std::fs::read(
+            manifest_path,
+        )?)?))
+    }
+}
+
+/// Returns true if the archive starting at the given block height has a manifest and the archive
+/// file matches the size in it. The checksum is only checked by `verify`.
+#[allow(dead_code)]
+pub fn is_archive_complete(path: &str, archive_height: BlockHeight) -> bool {
+    let Ok(Some(manifest)) = ArchiveManifest::load(path, archive_height) else {
+        return false;
+    };
+    std::fs::metadata(archive_path(path, archive_height, manifest.codec))
+        .map(|metadata| metadata.len() == manifest.size)
+        .unwrap_or(false)
+}
+
+/// Writes the content to a temporary file first and renames it once it's synced to the disk, so a
+/// partially written file is never visible under the given path, even after a crash.
+#[allow(dead_code)]
+pub fn write_atomically(file_path: &str, content: &[u8]) -> std::io::Result<()> {
+    create_atomically(file_path, |file| file.write_all(content))
+}
+
+/// Same as `write_atomically`, but the content is streamed into the temporary file by `write`.
+#[allow(dead_code)]
+pub fn create_atomically<T>(
+    file_path: &str,
+    write: impl FnOnce(&mut BufWriter<File>) -> std::io::Result<T>,
+) -> std::io::Result<T> {
+    let tmp_file_path = format!("{}.tmp", file_path);
+    let mut file = BufWriter::new(File::create(&tmp_file_path)?);
+    let result = write(&mut file)?;
+    file.flush()?;
+    // The content has to be durable before the rename, or a crash can leave a renamed file without
+    // its content, which `is_archive_complete` doesn't detect.
+    file.get_ref().sync_all()?;
+    drop(file);
+    std::fs::rename(&tmp_file_path, file_path)?;
+    // Making the rename itself durable.
+    if let Some(folder) = Path::new(file_path)
+        .parent()
+        .filter(|folder| !folder.as_os_str().is_empty())
+    {
+        File::open(folder)?.sync_all()?;
+    }
+    Ok(result)
+}
+
+/// A writer that computes the SHA-256 and the size of everything written through it.
+#[allow(dead_code)]
+pub struct Sha256Writer<W: Write> {
+    inner: W,
+    context: ring::digest::Context,
+    size: u64,
+}
+
+#[allow(dead_code)]
+impl<W: Write> Sha256Writer<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            context: ring::digest::Context::new(&ring::digest::SHA256),
+            size: 0,
+        }
+    }
+
+    /// Returns the hex SHA-256 and the size of the written content.
+    pub fn finish(self) -> (String, u64) {
+        (hex::encode(self.context.finish()), self.size)
+    }
+}
+
+impl<W: Write> Write for Sha256Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.context.update(&buf[..written]);
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
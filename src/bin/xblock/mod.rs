@@ -1,15 +1,20 @@
-use near_crypto::{PublicKey, Signature};
-use near_indexer::near_primitives::borsh::{BorshDeserialize, BorshSerialize};
-use near_indexer::near_primitives::challenge::ChallengesResult;
-use near_indexer::near_primitives::hash::CryptoHash;
-use near_indexer::near_primitives::serialize::dec_format;
-use near_indexer::near_primitives::types::{
+pub mod codec;
+mod convert;
+pub mod manifest;
+pub mod reader;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use fastnear_primitives::near_primitives::challenge::ChallengesResult;
+use fastnear_primitives::near_primitives::hash::CryptoHash;
+use fastnear_primitives::near_primitives::serialize::dec_format;
+use fastnear_primitives::near_primitives::types::{
     AccountId, Balance, BlockHeight, Gas, NumBlocks, ProtocolVersion, ShardId, StateRoot,
     StorageUsage, StoreKey, StoreValue,
 };
-use near_indexer::near_primitives::views::validator_stake_view::ValidatorStakeView;
-use near_indexer::near_primitives::views::AccessKeyView;
-use near_indexer::near_primitives::{borsh, views};
+use fastnear_primitives::near_primitives::views;
+use fastnear_primitives::near_primitives::views::validator_stake_view::ValidatorStakeView;
+use fastnear_primitives::near_primitives::views::AccessKeyView;
+use near_crypto::{PublicKey, Signature};
 use serde_with::base64::Base64;
 use serde_with::serde_as;
 
@@ -0,0 +1,72 @@
+use crate::xblock::codec::{ArchiveCodec, ZSTD_DICTIONARY_FILE};
+use crate::xblock::XBlock;
+use borsh::BorshDeserialize;
+use fastnear_primitives::near_primitives::types::BlockHeight;
+use std::io::Read;
+use std::path::Path;
+
+/// Every archive contains the blocks from a range of this many block heights.
+pub const ARCHIVE_SIZE: BlockHeight = 1000;
+
+/// Returns the first block height of the archive containing the given block height.
+pub fn archive_height(block_height: BlockHeight) -> BlockHeight {
+    block_height / ARCHIVE_SIZE * ARCHIVE_SIZE
+}
+
+/// Returns the path of the archive starting at the given block height:
+/// `<path>/<height / 10^6>/<height>.<codec extension>`.
+pub fn archive_path(path: &str, archive_height: BlockHeight, codec: ArchiveCodec) -> String {
+    let padded_height = format!("{:0>12}", archive_height);
+    format!(
+        "{}/{}/{}.{}",
+        path,
+        &padded_height[..6],
+        padded_height,
+        codec.extension()
+    )
+}
+
+/// Returns the path and the codec of the archive starting at the given block height, or None if
+/// the archive doesn't exist.
+#[allow(dead_code)]
+pub fn find_archive(path: &str, archive_height: BlockHeight) -> Option<(String, ArchiveCodec)> {
+    ArchiveCodec::ALL.into_iter().find_map(|codec| {
+        let archive_path = archive_path(path, archive_height, codec);
+        Path::new(&archive_path)
+            .exists()
+            .then_some((archive_path, codec))
+    })
+}
+
+/// Reads the blocks of the archive starting at the given block height, ordered by the block height.
+/// The codec is detected from the file extension. Returns None if the archive doesn't exist.
+#[allow(dead_code)]
+pub fn read_archive(
+    path: &str,
+    archive_height: BlockHeight,
+) -> std::io::Result<Option<Vec<XBlock>>> {
+    let Some((archive_path, codec)) = find_archive(path, archive_height) else {
+        return Ok(None);
+    };
+    let zstd_dictionary = match codec {
+        ArchiveCodec::Zstd => {
+            let dictionary_path = format!("{}/{}", path, ZSTD_DICTIONARY_FILE);
+            Path::new(&dictionary_path)
+                .exists()
+                .then(|| std::fs::read(&dictionary_path))
+                .transpose()?
+        }
+        _ => None,
+    };
+    let file = std::fs::File::open(archive_path)?;
+    let mut archive = tar::Archive::new(codec.decoder(file, zstd_dictionary.as_deref())?);
+    let mut blocks = vec![];
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let mut content = vec![];
+        entry.read_to_end(&mut content)?;
+        blocks.push(XBlock::try_from_slice(&content)?);
+    }
+    blocks.sort_by_key(|block| block.block.header.height);
+    Ok(Some(blocks))
+}